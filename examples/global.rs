@@ -0,0 +1,9 @@
+// Usa o AlphaAlocator como alocador global do binário inteiro
+alocator::global_alocator!(ALOCATOR);
+
+fn main() {
+    let v: Vec<u64> = (0..100).collect();
+    let s = format!("soma = {}", v.iter().sum::<u64>());
+    println!("{}", s);
+    ALOCATOR.print_historic();
+}
@@ -0,0 +1,168 @@
+//! Backends: quem decide onde cada bloco mora dentro da memória.
+//!
+//! O `AlphaAlocator` cuida do resto (trava, `free`, histórico, classes pequenas,
+//! diagnósticos) e só pergunta pro backend "onde cabe?" e "solta isso". O
+//! backend é escolhido pelo parâmetro de tipo `B`, igual a trava: o padrão é a
+//! tabela de slots ordenada ([`SlotTable`](crate::SlotTable)) e as alternativas
+//! são o [`Buddy`](crate::Buddy) e o [`Tlsf`](crate::Tlsf).
+
+use core::alloc::Layout;
+
+use crate::{AllocFailure, Diagnostic, PlacementPolicy, Slot};
+
+/// A memória que o backend administra, vista como ponteiro + tamanho.
+///
+/// Os offsets são sempre relativos a `base`, mas o alinhamento é calculado em
+/// cima do endereço de verdade (`base + offset`).
+#[derive(Clone, Copy)]
+pub struct Region {
+    base: *mut u8,
+    len: usize,
+}
+
+impl Region {
+    pub(crate) fn new(base: *mut u8, len: usize) -> Self {
+        Region { base, len }
+    }
+
+    pub fn base(&self) -> *mut u8 {
+        self.base
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Arredonda `offset` pra cima até que `base + offset` fique múltiplo de `align`.
+    /// Retorna None se estourar (align gigante, tipo quando o offset já tá no fim).
+    pub fn align_up(&self, offset: usize, align: usize) -> Option<usize> {
+        let base = self.base as usize;
+        let addr = base.checked_add(offset)?;
+        let aligned = addr.checked_add(align - 1)? & !(align - 1);
+        Some(aligned - base)
+    }
+
+    /// Tenta encaixar `size` bytes alinhados em `align` no buraco [start, end).
+    /// Retorna o offset já alinhado se couber (contando o padding do alinhamento).
+    pub fn fit(&self, start: usize, end: usize, size: usize, align: usize) -> Option<usize> {
+        let offset = self.align_up(start, align)?;
+        if offset <= end && end - offset >= size {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
+
+/// Um bloco entregue pelo backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// Offset que vira o ponteiro do usuário.
+    pub offset: usize,
+    /// Quantos bytes o bloco realmente ocupa (o buddy arredonda pra potência de 2).
+    pub reserved: usize,
+}
+
+/// Estratégia de organização dos blocos dentro da [`Region`].
+///
+/// Todos os métodos rodam com a trava do alocador pega, então `&mut self` é
+/// exclusivo de verdade.
+///
+/// # Safety
+///
+/// Quem implementa garante que os blocos vivos nunca se sobrepõem, que ficam
+/// dentro da região e alinhados como pedido, e que o backend só escreve na
+/// região fora dos blocos vivos.
+pub unsafe trait Backend {
+    /// Estado inicial (sem nenhum bloco), pra montar tudo em `const fn`.
+    const INIT: Self;
+
+    /// true se o backend nunca escreve na memória livre. Aí tudo acima do high
+    /// water mark continua zerado e o `alloc_zeroed` pode pular esse pedaço.
+    const KEEPS_FREE_MEMORY_CLEAN: bool;
+
+    /// Aloca `layout.size()` bytes alinhados em `layout.align()`.
+    /// A `policy` é só uma sugestão (backends que não varrem buraco ignoram).
+    fn alloc(&mut self, region: Region, layout: Layout, policy: PlacementPolicy) -> Result<Block, AllocFailure>;
+
+    /// Solta o bloco entregue em `offset` com esse `layout`.
+    /// Retorna quantos bytes voltaram, ou None se não tinha bloco vivo ali.
+    fn dealloc(&mut self, region: Region, offset: usize, layout: Layout) -> Option<usize>;
+
+    /// Confere, sem mexer em nada, se `offset` é o ponteiro que o backend
+    /// entregou pra um bloco vivo pedido com esse `layout`. Só o modo checado
+    /// chama (antes do `dealloc`), então pode ser bem mais lento que ele.
+    fn check(&self, region: Region, offset: usize, layout: Layout) -> Result<(), Diagnostic>;
+
+    /// Tenta mudar o tamanho do bloco sem mover.
+    /// Retorna `(reservado antes, reservado depois)` se deu.
+    fn resize(&mut self, region: Region, offset: usize, layout: Layout, new_size: usize) -> Option<(usize, usize)>;
+
+    /// Quantos bytes da região o próprio backend tá usando pra se organizar
+    /// (tabelas, bitmaps, sobra que não dá pra usar). O alocador desconta isso
+    /// do `free`.
+    fn metadata_bytes(&self, region: Region) -> usize;
+
+    /// Chama `f` pra cada bloco vivo, em ordem de offset. O `Slot` é o bloco
+    /// inteiro que o backend reservou, que pode começar antes do ponteiro
+    /// entregue (cabeçalho do TLSF, folga de alinhamento).
+    fn for_each_block(&self, region: Region, f: &mut dyn FnMut(Slot));
+
+    /// Pedaço de `slot` (um bloco passado pelo `for_each_block`) que o pedido
+    /// ocupa de verdade; o resto é cabeçalho ou sobra de arredondamento. O
+    /// padrão é o bloco inteiro, pra backend que reserva o tamanho exato.
+    fn requested(&self, _region: Region, slot: Slot) -> Slot {
+        slot
+    }
+
+    /// Chama `f(início, fim)` pra cada buraco livre, em ordem de offset. Cada
+    /// buraco é um pedaço que dá pra usar inteiro num pedido só (dois livres
+    /// vizinhos que o backend não consegue juntar aparecem separados), sem
+    /// contar metadados. Antes do primeiro uso pode ser a região inteira.
+    fn for_each_gap(&self, region: Region, f: &mut dyn FnMut(usize, usize));
+}
+
+/// Bloco vivo que contém `offset`, passando por todos (O(n), serve pro `check`
+/// de backend que não tem jeito melhor de achar).
+pub(crate) fn live_block_containing<B: Backend>(backend: &B, region: Region, offset: usize) -> Option<Slot> {
+    let mut found = None;
+    backend.for_each_block(region, &mut |slot| {
+        if offset >= slot.index && offset < slot.index + slot.size {
+            found = Some(slot);
+        }
+    });
+    found
+}
+
+/// Guarda `slack` (> 0) no fim do bloco que termina em `end`, em bytes que não
+/// são do usuário: o último byte é a própria sobra, ou 0xFF com ela inteira
+/// nos 4 bytes de antes quando não cabe num byte (aí tem espaço de sobra).
+pub(crate) fn write_slack(region: Region, end: usize, slack: usize) {
+    unsafe {
+        let last = region.base().add(end - 1);
+        if slack < 0xFF {
+            *last = slack as u8;
+        } else {
+            *last = 0xFF;
+            (last.sub(4) as *mut u32).write_unaligned(slack as u32);
+        }
+    }
+}
+
+/// Lê o que o `write_slack` guardou no bloco que termina em `end`, no máximo
+/// `max` (se o usuário escreveu além do pedido ali pode ter lixo).
+pub(crate) fn read_slack(region: Region, end: usize, max: usize) -> usize {
+    let slack = unsafe {
+        let last = region.base().add(end - 1);
+        if *last < 0xFF {
+            *last as usize
+        } else {
+            (last.sub(4) as *const u32).read_unaligned() as usize
+        }
+    };
+    slack.min(max)
+}
@@ -0,0 +1,409 @@
+//! Roda um trace (exportado com `AlphaAlocator::write_trace`) de novo num
+//! alocador com outra configuração e conta se e onde a memória teria acabado.
+//! Serve pra dimensionar a memória de um firmware a partir de uma captura real.
+//!
+//! ```text
+//! cargo run --bin replay -- captura.trace --mem 16384 --policy best --backend tlsf
+//! cargo run --bin replay -- captura.trace --sweep --backend tlsf
+//! ```
+//!
+//! O alocador é compilado uma vez só, com [`MAX_MEM`] bytes (1 MiB, ou o da
+//! variável `REPLAY_MEM` na hora de compilar), e cada rodada usa só o começo
+//! dela com o `with_memory_limit`, que se comporta igual a um alocador
+//! daquele tamanho. Na linha de comando:
+//!
+//! - `--mem <bytes>`: qualquer tamanho até o `MAX_MEM` (padrão: `MEMORY_SIZE`)
+//! - `--sweep`: acha o menor tamanho em que coube tudo (no lugar do `--mem`),
+//!   dobrando a partir de 4 KiB e depois com busca binária de 16 em 16 bytes.
+//!   A busca supõe que memória maior nunca atrapalha, o que com fragmentação
+//!   nem sempre vale por poucos bytes
+//! - `--policy first|next|best|worst` (padrão: `first`)
+//! - `--backend slots|buddy|tlsf` (padrão: `slots`)
+//! - `--arenas 1|2|4|8` (padrão: 1)
+//! - `--size-classes`
+//!
+//! O trace vem do arquivo (ou do stdin, com `-`). Só os eventos que deram
+//! certo na captura são repetidos (os que falharam ou foram recusados não
+//! mudaram nada lá). Os blocos são casados pelo offset da captura, então um
+//! `dealloc` de bloco que a gente não conhece (alocado antes do começo do
+//! trace, ou que faltou memória aqui) é pulado. Um bloco novo num offset que
+//! ainda tá vivo (trace fora de ordem ou faltando evento) é avisado e pulado.
+//!
+//! Sai com 1 se faltou memória alguma vez (no `--sweep`, se não coube em
+//! nenhum tamanho).
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+use std::{env, fs, process, thread};
+
+use alocator::{
+    AllocFailure, AlphaAlocator, Backend, Buddy, Event, EventKind, FailureSnapshot, Outcome, PlacementPolicy,
+    SlotTable, SpinLock, Tlsf, MEMORY_SIZE,
+};
+
+/// Maior memória que dá pra pedir, do `REPLAY_MEM` (padrão: 1 MiB).
+const MAX_MEM: usize = match option_env!("REPLAY_MEM") {
+    Some(text) => parse_mem(text),
+    None => 1 << 20,
+};
+
+const fn parse_mem(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    assert!(!bytes.is_empty(), "REPLAY_MEM vazio");
+    let mut mem = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "REPLAY_MEM tem que ser um número em bytes");
+        mem = mem * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    mem
+}
+
+// Primeiro tamanho do `--sweep` e a precisão da busca
+const SWEEP_START: usize = 4096;
+const SWEEP_STEP: usize = 16;
+
+const USAGE: &str = "uso: replay <trace|-> [--mem <bytes> | --sweep] [--policy first|next|best|worst] \
+                     [--backend slots|buddy|tlsf] [--arenas 1|2|4|8] [--size-classes]";
+
+// Quantas falhas são mostradas uma por uma (o resto só entra na conta)
+const SHOWN_FAILURES: usize = 10;
+
+struct Config {
+    path: String,
+    mem: usize,
+    sweep: bool,
+    policy: PlacementPolicy,
+    backend: String,
+    arenas: usize,
+    size_classes: bool,
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut args = env::args().skip(1);
+    let mut config = Config {
+        path: String::new(),
+        mem: MEMORY_SIZE.min(MAX_MEM),
+        sweep: false,
+        policy: PlacementPolicy::FirstFit,
+        backend: "slots".into(),
+        arenas: 1,
+        size_classes: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mem" => {
+                config.mem = value(&mut args, "--mem")?.parse().map_err(|_| "--mem tem que ser número")?;
+                if config.mem > MAX_MEM {
+                    return Err(format!("--mem {} passa do máximo compilado ({MAX_MEM}, o REPLAY_MEM)", config.mem));
+                }
+            }
+            "--sweep" => config.sweep = true,
+            "--policy" => {
+                config.policy = match value(&mut args, "--policy")?.as_str() {
+                    "first" => PlacementPolicy::FirstFit,
+                    "next" => PlacementPolicy::NextFit,
+                    "best" => PlacementPolicy::BestFit,
+                    "worst" => PlacementPolicy::WorstFit,
+                    other => return Err(format!("política desconhecida: {other}")),
+                }
+            }
+            "--backend" => {
+                config.backend = value(&mut args, "--backend")?;
+                if !["slots", "buddy", "tlsf"].contains(&config.backend.as_str()) {
+                    return Err(format!("backend desconhecido: {}", config.backend));
+                }
+            }
+            "--arenas" => {
+                config.arenas = value(&mut args, "--arenas")?.parse().map_err(|_| "--arenas tem que ser número")?;
+                if ![1, 2, 4, 8].contains(&config.arenas) {
+                    return Err(format!("--arenas {} não tá compilado (só 1, 2, 4 ou 8)", config.arenas));
+                }
+            }
+            "--size-classes" => config.size_classes = true,
+            _ if config.path.is_empty() && (arg == "-" || !arg.starts_with('-')) => config.path = arg,
+            other => return Err(format!("argumento desconhecido: {other}")),
+        }
+    }
+    if config.path.is_empty() {
+        return Err("faltou o arquivo do trace".into());
+    }
+    if !config.sweep && config.mem < 16 * config.arenas {
+        return Err(format!("--mem {} é pouco pra {} arena(s)", config.mem, config.arenas));
+    }
+    Ok(config)
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("faltou o valor de {flag}"))
+}
+
+fn read_trace(path: &str) -> Result<Vec<Event>, String> {
+    let input: Box<dyn Read> = if path == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(path).map_err(|e| format!("{path}: {e}"))?)
+    };
+    let mut events = Vec::new();
+    for (n, line) in BufReader::new(input).lines().enumerate() {
+        let line = line.map_err(|e| format!("{path}: {e}"))?;
+        if let Some(event) = Event::from_trace(&line).map_err(|e| format!("{path}:{}: {e}", n + 1))? {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+#[derive(Default)]
+struct Report {
+    // Sem mostrar cada falha (no `--sweep`)
+    quiet: bool,
+    failures: usize,
+    first_failure: Option<u64>,
+    skipped: usize,
+    // Blocos novos em offset que já tava vivo
+    duplicates: usize,
+    live_bytes: usize,
+    peak_bytes: usize,
+    // Pico do alocador, com o que o backend reservou a mais e os metadados
+    peak_used: usize,
+}
+
+/// Repete os eventos num alocador novo que usa `mem` bytes, com o backend
+/// `B` e `ARENAS` arenas.
+fn replay<B: Backend + Send, const ARENAS: usize>(
+    config: &Config,
+    events: &[Event],
+    mem: usize,
+    quiet: bool,
+) -> Report {
+    let a = AlphaAlocator::<MAX_MEM, 0, SpinLock, B, ARENAS>::with_policy(config.policy)
+        .with_memory_limit(mem)
+        .with_failure_snapshots();
+    let a = Box::new(if config.size_classes { a.with_size_classes() } else { a });
+
+    let mut report = Report {
+        quiet,
+        ..Report::default()
+    };
+    // Offset na captura -> bloco aqui
+    let mut live: HashMap<usize, (*mut u8, Layout)> = HashMap::new();
+    for event in events {
+        if event.outcome != Outcome::Ok {
+            continue;
+        }
+        let Ok(layout) = Layout::from_size_align(event.size, event.align) else {
+            report.skipped += 1;
+            continue;
+        };
+        match event.kind {
+            EventKind::Alloc | EventKind::AllocZeroed | EventKind::Realloc { old_size: 0, .. } => {
+                // Sem offset é pedido de tamanho zero, não ocupa nada
+                let Some(offset) = event.offset else { continue };
+                if report.duplicate(&live, offset, None, event) {
+                    continue;
+                }
+                let ptr = unsafe { a.alloc(layout) };
+                if ptr.is_null() {
+                    report.fail(event, a.last_failure(), a.last_failure_snapshot());
+                    continue;
+                }
+                live.insert(offset, (ptr, layout));
+                report.live_bytes += layout.size();
+            }
+            EventKind::Dealloc => {
+                let Some((ptr, layout)) = event.offset.and_then(|offset| live.remove(&offset)) else {
+                    report.skipped += 1;
+                    continue;
+                };
+                unsafe { a.dealloc(ptr, layout) };
+                report.live_bytes -= layout.size();
+            }
+            EventKind::Realloc { old_offset, .. } => {
+                let Some(old_offset) = old_offset.filter(|offset| live.contains_key(offset)) else {
+                    report.skipped += 1;
+                    continue;
+                };
+                if event.offset.is_some_and(|offset| report.duplicate(&live, offset, Some(old_offset), event)) {
+                    continue;
+                }
+                let (ptr, old_layout) = live.remove(&old_offset).unwrap();
+                let new_ptr = unsafe { a.realloc(ptr, old_layout, event.size) };
+                if let Some(offset) = event.offset.filter(|_| new_ptr.is_null()) {
+                    // O bloco antigo continua vivo aqui, mas na captura ele já
+                    // tá no offset novo: é por ele que os próximos eventos chamam
+                    live.insert(offset, (ptr, old_layout));
+                    report.fail(event, a.last_failure(), a.last_failure_snapshot());
+                    continue;
+                }
+                report.live_bytes -= old_layout.size();
+                if let Some(offset) = event.offset {
+                    live.insert(offset, (new_ptr, layout));
+                    report.live_bytes += layout.size();
+                }
+            }
+        }
+        report.peak_bytes = report.peak_bytes.max(report.live_bytes);
+    }
+    report.peak_used = a.stats().peak_bytes;
+    report
+}
+
+impl Report {
+    /// O offset novo do evento já tá vivo (sem contar o `except`, que o
+    /// próprio evento solta)? Se tiver, avisa: sobrescrever perderia o bloco
+    /// que tava lá.
+    fn duplicate(
+        &mut self,
+        live: &HashMap<usize, (*mut u8, Layout)>,
+        offset: usize,
+        except: Option<usize>,
+        event: &Event,
+    ) -> bool {
+        if except == Some(offset) || !live.contains_key(&offset) {
+            return false;
+        }
+        if !self.quiet && self.duplicates < SHOWN_FAILURES {
+            println!("offset {} já tava vivo: {} (trace fora de ordem ou faltando evento)", offset, event);
+        }
+        self.duplicates += 1;
+        true
+    }
+
+    fn fail(&mut self, event: &Event, reason: Option<AllocFailure>, snapshot: Option<FailureSnapshot>) {
+        if !self.quiet && self.failures < SHOWN_FAILURES {
+            println!("faltou memória: {} ({:?}, com {} bytes vivos)", event, reason, self.live_bytes);
+            if let Some(snapshot) = snapshot {
+                let f = snapshot.fragmentation;
+                println!(
+                    "  livre: {} bytes em {} buracos, maior {} (fragmentação {:.2})",
+                    f.free_bytes,
+                    f.gaps,
+                    f.largest_gap,
+                    f.ratio()
+                );
+            }
+        }
+        self.failures += 1;
+        self.first_failure.get_or_insert(event.seq);
+    }
+}
+
+macro_rules! with_arenas {
+    ($backend:ty, $args:tt) => {
+        match $args.0.arenas {
+            1 => replay::<$backend, 1> $args,
+            2 => replay::<$backend, 2> $args,
+            4 => replay::<$backend, 4> $args,
+            _ => replay::<$backend, 8> $args,
+        }
+    };
+}
+
+/// Roda o replay usando `mem` bytes da memória.
+fn run(config: &Config, events: &[Event], mem: usize, quiet: bool) -> Report {
+    // O alocador é montado na pilha antes de ir pro heap, então a thread
+    // precisa de pilha pra umas cópias da memória inteira
+    thread::scope(|s| {
+        thread::Builder::new()
+            .stack_size(8 * MAX_MEM + (1 << 20))
+            .spawn_scoped(s, || match config.backend.as_str() {
+                "buddy" => with_arenas!(Buddy, (config, events, mem, quiet)),
+                "tlsf" => with_arenas!(Tlsf, (config, events, mem, quiet)),
+                _ => with_arenas!(SlotTable, (config, events, mem, quiet)),
+            })
+            .expect("não deu pra criar a thread do replay")
+            .join()
+            .expect("replay deu panic")
+    })
+}
+
+/// `--sweep`: dobra a memória até caber tudo e depois faz busca binária
+/// entre o último tamanho que não coube e o primeiro que coube.
+fn sweep(config: &Config, events: &[Event]) -> ! {
+    let fits = |mem: usize| {
+        let report = run(config, events, mem, true);
+        match report.first_failure {
+            None => {
+                let (peak, used) = (report.peak_bytes, report.peak_used);
+                println!("{mem} bytes: coube (pico de {peak} bytes vivos, ocupando {used})")
+            }
+            Some(seq) => println!("{mem} bytes: faltou memória {} vez(es), a primeira no #{}", report.failures, seq),
+        }
+        report.first_failure.is_none()
+    };
+
+    // `low` é um passo abaixo do menor tamanho que dá pras arenas
+    let (mut low, mut high) = (16 * config.arenas - SWEEP_STEP, SWEEP_START.min(MAX_MEM));
+    while !fits(high) {
+        if high == MAX_MEM {
+            println!("não coube nem no máximo compilado ({MAX_MEM} bytes, o REPLAY_MEM)");
+            process::exit(1);
+        }
+        low = high;
+        high = (2 * high).min(MAX_MEM);
+    }
+    // `low` não coube (ou é pequeno demais pras arenas), `high` coube
+    while high - low > SWEEP_STEP {
+        let mid = (low + (high - low) / 2) / SWEEP_STEP * SWEEP_STEP;
+        let mid = if mid <= low { low + SWEEP_STEP } else { mid };
+        if fits(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    println!("menor memória em que coube tudo: {high} bytes");
+    process::exit(0);
+}
+
+fn main() {
+    let config = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(2);
+    });
+    let events = read_trace(&config.path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+
+    println!(
+        "memória {}, backend {}, {} arena(s), {:?}{}",
+        if config.sweep { "em varredura".to_string() } else { format!("{} bytes", config.mem) },
+        config.backend,
+        config.arenas,
+        config.policy,
+        if config.size_classes { ", classes pequenas" } else { "" }
+    );
+    match (events.first(), events.last()) {
+        (Some(first), Some(last)) => {
+            println!("trace: {} eventos, do #{} ao #{}", events.len(), first.seq, last.seq);
+            if first.seq != 0 {
+                println!("aviso: o trace não começa no #0, os blocos alocados antes dele não estão aqui");
+            }
+        }
+        _ => println!("trace vazio"),
+    }
+
+    if config.sweep {
+        sweep(&config, &events);
+    }
+    let report = run(&config, &events, config.mem, false);
+
+    println!("pico de bytes vivos: {} (ocupando {} da memória)", report.peak_bytes, report.peak_used);
+    if report.skipped > 0 {
+        println!("eventos pulados (bloco desconhecido): {}", report.skipped);
+    }
+    if report.duplicates > 0 {
+        println!("eventos pulados (offset já vivo): {}", report.duplicates);
+    }
+    match report.first_failure {
+        None => println!("coube tudo"),
+        Some(seq) => {
+            println!("faltou memória {} vez(es), a primeira no #{}", report.failures, seq);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,374 @@
+//! Backend buddy: blocos de potência de 2, divididos ao meio quando sobra e
+//! juntados com o "irmão" (buddy) quando os dois ficam livres.
+//!
+//! Tudo mora dentro da própria memória: no começo ficam três bitmaps (um bit
+//! por unidade de [`MIN_BLOCK`] bytes, "começa bloco livre aqui", "começa
+//! bloco em uso aqui" e "esse bloco em uso tem sobra anotada no fim"), e cada
+//! bloco livre guarda no próprio corpo os links da free list da sua ordem. Não
+//! tem limite de blocos vivos além da memória.
+//!
+//! Os blocos são alinhados no próprio tamanho pelo endereço de verdade (não
+//! pelo offset), então todo bloco já sai com o alinhamento natural e um
+//! pedido com alinhamento grande só precisa de um bloco desse tamanho.
+
+use core::alloc::Layout;
+
+use crate::backend::{live_block_containing, read_slack, write_slack, Backend, Block, Region};
+use crate::{AllocFailure, Diagnostic, PlacementPolicy, Slot};
+
+/// Menor bloco que o buddy entrega (cabe o cabeçalho de bloco livre).
+pub const MIN_BLOCK: usize = 1 << MIN_ORDER;
+const MIN_ORDER: u32 = 4;
+// Offsets são u32, então 32 ordens bastam
+const ORDERS: usize = 32;
+// Fim da free list
+const NIL: u32 = u32::MAX;
+
+/// Cabeçalho guardado no começo de cada bloco livre.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct FreeHeader {
+    next: u32,
+    prev: u32,
+    order: u32,
+}
+
+/// Backend buddy (ver doc do módulo). Ignora a `PlacementPolicy`: o lugar de
+/// cada bloco é sempre o primeiro da free list da menor ordem que cabe.
+pub struct Buddy {
+    heads: [u32; ORDERS],
+    // Os bitmaps só são montados no primeiro uso (precisa da região pra isso)
+    ready: bool,
+    bitmap_bytes: usize,
+    // Blocos ficam em [meta_end, end); antes são os bitmaps, depois é sobra < MIN_BLOCK
+    meta_end: usize,
+    end: usize,
+    free_bytes: usize,
+}
+
+/// Qual dos três bitmaps.
+#[derive(Clone, Copy)]
+enum Bits {
+    Free,
+    Used,
+    Slack,
+}
+
+impl Buddy {
+    pub const fn new() -> Self {
+        Buddy {
+            heads: [NIL; ORDERS],
+            ready: false,
+            bitmap_bytes: 0,
+            meta_end: 0,
+            end: 0,
+            free_bytes: 0,
+        }
+    }
+
+    /// Monta os bitmaps e cobre o resto da memória com os maiores blocos que
+    /// couberem (alinhados no próprio tamanho, pelo endereço).
+    fn init(&mut self, region: Region) {
+        let units = region.len() / MIN_BLOCK;
+        self.bitmap_bytes = units.div_ceil(8);
+        self.meta_end = region.align_up(3 * self.bitmap_bytes, MIN_BLOCK).unwrap_or(region.len());
+        unsafe { core::ptr::write_bytes(region.base(), 0, (3 * self.bitmap_bytes).min(region.len())) };
+
+        let mut offset = self.meta_end;
+        while offset + MIN_BLOCK <= region.len() {
+            let mut order = MIN_ORDER;
+            while (order as usize) + 1 < ORDERS
+                && Self::addr(region, offset).is_multiple_of(1 << (order + 1))
+                && offset + (1 << (order + 1)) <= region.len()
+            {
+                order += 1;
+            }
+            self.push_free(region, offset, order);
+            offset += 1 << order;
+        }
+        self.end = offset;
+        self.ready = true;
+    }
+
+    /// Endereço de verdade do offset (é nele que o alinhamento dos blocos conta).
+    fn addr(region: Region, offset: usize) -> usize {
+        region.base() as usize + offset
+    }
+
+    /// Offset do buddy do bloco em `offset` na ordem `order` (None se ele
+    /// cairia antes do começo da memória).
+    fn buddy_of(region: Region, offset: usize, order: u32) -> Option<usize> {
+        (Self::addr(region, offset) ^ (1 << order)).checked_sub(region.base() as usize)
+    }
+
+    /// Ordem do bloco que atende o layout. Todo bloco é alinhado no próprio
+    /// tamanho, então basta ele ser do tamanho do alinhamento.
+    fn order_of(size: usize, align: usize) -> Option<u32> {
+        let order = size.max(align).max(MIN_BLOCK).checked_next_power_of_two()?.trailing_zeros();
+        if (order as usize) < ORDERS {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    fn bit(&self, region: Region, bits: Bits, offset: usize) -> bool {
+        let (byte, mask) = self.bit_pos(bits, offset);
+        unsafe { *region.base().add(byte) & mask != 0 }
+    }
+
+    fn set_bit(&self, region: Region, bits: Bits, offset: usize, on: bool) {
+        let (byte, mask) = self.bit_pos(bits, offset);
+        unsafe {
+            let b = region.base().add(byte);
+            if on {
+                *b |= mask;
+            } else {
+                *b &= !mask;
+            }
+        }
+    }
+
+    fn bit_pos(&self, bits: Bits, offset: usize) -> (usize, u8) {
+        let unit = offset / MIN_BLOCK;
+        let start = match bits {
+            Bits::Free => 0,
+            Bits::Used => self.bitmap_bytes,
+            Bits::Slack => 2 * self.bitmap_bytes,
+        };
+        (start + unit / 8, 1 << (unit % 8))
+    }
+
+    fn header(region: Region, offset: usize) -> FreeHeader {
+        unsafe { (region.base().add(offset) as *const FreeHeader).read_unaligned() }
+    }
+
+    fn set_header(region: Region, offset: usize, header: FreeHeader) {
+        unsafe { (region.base().add(offset) as *mut FreeHeader).write_unaligned(header) }
+    }
+
+    fn push_free(&mut self, region: Region, offset: usize, order: u32) {
+        let head = self.heads[order as usize];
+        Self::set_header(region, offset, FreeHeader { next: head, prev: NIL, order });
+        if head != NIL {
+            let mut h = Self::header(region, head as usize);
+            h.prev = offset as u32;
+            Self::set_header(region, head as usize, h);
+        }
+        self.heads[order as usize] = offset as u32;
+        self.set_bit(region, Bits::Free, offset, true);
+        self.free_bytes += 1 << order;
+    }
+
+    fn remove_free(&mut self, region: Region, offset: usize, order: u32) {
+        let h = Self::header(region, offset);
+        if h.prev == NIL {
+            self.heads[order as usize] = h.next;
+        } else {
+            let mut p = Self::header(region, h.prev as usize);
+            p.next = h.next;
+            Self::set_header(region, h.prev as usize, p);
+        }
+        if h.next != NIL {
+            let mut n = Self::header(region, h.next as usize);
+            n.prev = h.prev;
+            Self::set_header(region, h.next as usize, n);
+        }
+        self.set_bit(region, Bits::Free, offset, false);
+        self.free_bytes -= 1 << order;
+    }
+
+    /// O bloco em `offset` tá livre e inteiro na ordem `order`?
+    fn is_free_at(&self, region: Region, offset: usize, order: u32) -> bool {
+        offset >= self.meta_end
+            && offset + (1 << order) <= self.end
+            && self.bit(region, Bits::Free, offset)
+            && Self::header(region, offset).order == order
+    }
+
+    /// Anota no bloco em uso quanto sobra depois dos `requested` bytes do
+    /// pedido (só o mapa lê).
+    fn set_slack(&self, region: Region, block: usize, order: u32, requested: usize) {
+        let slack = (1 << order) - requested;
+        self.set_bit(region, Bits::Slack, block, slack > 0);
+        if slack > 0 {
+            write_slack(region, block + (1 << order), slack);
+        }
+    }
+
+    /// Começo e ordem do bloco que foi entregue em `offset` com esse layout.
+    fn block_of(&self, region: Region, offset: usize, layout: Layout) -> Option<(usize, u32)> {
+        let order = Self::order_of(layout.size(), layout.align())?;
+        // Confere que era mesmo um bloco vivo e que o ponteiro é o que a gente
+        // deu (o começo do bloco, que já sai alinhado)
+        let valid = self.ready
+            && offset >= self.meta_end
+            && offset + (1 << order) <= self.end
+            && Self::addr(region, offset).is_multiple_of(1 << order)
+            && self.bit(region, Bits::Used, offset);
+        if valid {
+            Some((offset, order))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Buddy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl Backend for Buddy {
+    const INIT: Self = Buddy::new();
+    // Cabeçalhos de bloco livre e bitmaps sujam a memória livre
+    const KEEPS_FREE_MEMORY_CLEAN: bool = false;
+
+    fn alloc(&mut self, region: Region, layout: Layout, _policy: PlacementPolicy) -> Result<Block, AllocFailure> {
+        if !self.ready {
+            self.init(region);
+        }
+        let order = Self::order_of(layout.size(), layout.align()).ok_or(AllocFailure::OutOfMemory)?;
+
+        // Menor ordem com bloco livre que dá conta
+        let Some(mut current) = (order..ORDERS as u32).find(|&o| self.heads[o as usize] != NIL) else {
+            return Err(if self.free_bytes < 1 << order {
+                AllocFailure::OutOfMemory
+            } else {
+                AllocFailure::Fragmentation
+            });
+        };
+        let block = self.heads[current as usize] as usize;
+        self.remove_free(region, block, current);
+
+        // Divide ao meio até chegar na ordem pedida, devolvendo as metades de cima
+        while current > order {
+            current -= 1;
+            self.push_free(region, block + (1 << current), current);
+        }
+        self.set_bit(region, Bits::Used, block, true);
+        self.set_slack(region, block, order, layout.size());
+        Ok(Block { offset: block, reserved: 1 << order })
+    }
+
+    fn dealloc(&mut self, region: Region, offset: usize, layout: Layout) -> Option<usize> {
+        let (mut block, order) = self.block_of(region, offset, layout)?;
+        self.set_bit(region, Bits::Used, block, false);
+
+        // Junta com o buddy enquanto ele também estiver livre e inteiro
+        let mut current = order;
+        while (current as usize) + 1 < ORDERS {
+            let Some(buddy) = Self::buddy_of(region, block, current) else {
+                break;
+            };
+            if !self.is_free_at(region, buddy, current) {
+                break;
+            }
+            self.remove_free(region, buddy, current);
+            block = block.min(buddy);
+            current += 1;
+        }
+        self.push_free(region, block, current);
+        Some(1 << order)
+    }
+
+    fn check(&self, region: Region, offset: usize, layout: Layout) -> Result<(), Diagnostic> {
+        // Não dá pra confiar só no `block_of`: com um layout menor ele acha a
+        // metade de baixo do bloco e aceita, então compara com o bloco de verdade
+        let Some(slot) = live_block_containing(self, region, offset) else {
+            return Err(Diagnostic::DoubleFree { offset });
+        };
+        if slot.index != offset {
+            return Err(Diagnostic::InteriorPointer { offset, block: slot.index });
+        }
+        // O tamanho tem que ser o pedido exato (a sobra fica anotada no bloco)
+        // e o alinhamento tem que dar a mesma ordem
+        let requested = self.requested(region, slot).size;
+        match Self::order_of(layout.size(), layout.align()) {
+            Some(order) if 1 << order == slot.size && layout.size() == requested => Ok(()),
+            _ => Err(Diagnostic::SizeMismatch { offset, expected: requested, got: layout.size() }),
+        }
+    }
+
+    fn resize(&mut self, region: Region, offset: usize, layout: Layout, new_size: usize) -> Option<(usize, usize)> {
+        let (block, old_order) = self.block_of(region, offset, layout)?;
+        let new_order = Self::order_of(new_size, layout.align())?;
+
+        if new_order < old_order {
+            // Encolher: devolve as metades de cima, do maior pedaço pro menor
+            for o in (new_order..old_order).rev() {
+                self.push_free(region, block + (1 << o), o);
+            }
+        } else if new_order > old_order {
+            // Crescer: o bloco tem que ser a metade de baixo em todas as ordens
+            // e cada metade de cima tem que estar livre e inteira
+            if !Self::addr(region, block).is_multiple_of(1 << new_order)
+                || !(old_order..new_order).all(|o| self.is_free_at(region, block + (1 << o), o))
+            {
+                return None;
+            }
+            for o in old_order..new_order {
+                self.remove_free(region, block + (1 << o), o);
+            }
+        }
+        self.set_slack(region, block, new_order, new_size);
+        Some((1 << old_order, 1 << new_order))
+    }
+
+    fn metadata_bytes(&self, region: Region) -> usize {
+        if self.ready {
+            self.meta_end + (region.len() - self.end)
+        } else {
+            0
+        }
+    }
+
+    fn for_each_block(&self, region: Region, f: &mut dyn FnMut(Slot)) {
+        if !self.ready {
+            return;
+        }
+        // Os blocos cobrem [meta_end, end) sem buraco: livre a gente pula pela
+        // ordem do cabeçalho, em uso vai até o próximo começo de bloco
+        let mut offset = self.meta_end;
+        while offset < self.end {
+            if self.bit(region, Bits::Free, offset) {
+                offset += 1 << Self::header(region, offset).order;
+                continue;
+            }
+            let mut next = offset + MIN_BLOCK;
+            while next < self.end && !self.bit(region, Bits::Free, next) && !self.bit(region, Bits::Used, next) {
+                next += MIN_BLOCK;
+            }
+            f(Slot { size: next - offset, index: offset });
+            offset = next;
+        }
+    }
+
+    fn requested(&self, region: Region, slot: Slot) -> Slot {
+        if !self.bit(region, Bits::Slack, slot.index) {
+            return slot;
+        }
+        let size = slot.size - read_slack(region, slot.index + slot.size, slot.size);
+        Slot { size, index: slot.index }
+    }
+
+    fn for_each_gap(&self, region: Region, f: &mut dyn FnMut(usize, usize)) {
+        if !self.ready {
+            // Ainda não montou os bitmaps: tudo livre
+            return f(0, region.len());
+        }
+        // Cada bloco livre é um buraco: dois livres vizinhos que não são
+        // buddies não se juntam, então nenhum pedido usa os dois
+        let mut offset = self.meta_end;
+        while offset < self.end {
+            if self.bit(region, Bits::Free, offset) {
+                let size = 1 << Self::header(region, offset).order;
+                f(offset, offset + size);
+                offset += size;
+            } else {
+                offset += MIN_BLOCK;
+            }
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Cache por thread na frente da memória compartilhada (só com `std`).
+//!
+//! Cada thread guarda, por alocador, um "magazine" por classe pequena com os
+//! últimos blocos que ela soltou. O próximo pedido da mesma classe sai dali
+//! sem pegar trava nenhuma. Os blocos continuam vivos pro backend enquanto
+//! estão no cache (ele nem sabe que o cache existe).
+//!
+//! Pra não prender memória demais:
+//! - cada thread segura no máximo [`THREAD_LIMIT`] bytes por alocador (e
+//!   [`MAGAZINE`] blocos por classe). Passou disso, metade do magazine volta
+//!   pro alocador;
+//! - a cada [`FLUSH_EVERY`] operações no cache, metade de cada magazine volta;
+//! - [`flush`] (o `AlphaAlocator::flush_thread_cache`) devolve tudo na hora;
+//! - quando a thread termina, tudo volta (no unix, ver abaixo).
+//!
+//! O cache em si não tem destrutor: em várias plataformas (Windows, macOS,
+//! musl) registrar o destrutor de uma thread-local aloca, o que entraria de
+//! volta no alocador no meio do registro e o std aborta. No unix quem devolve
+//! é um destrutor de `pthread_key_create`, que não passa pelo alocador do
+//! Rust. Nas outras plataformas a thread que termina sem chamar o `flush`
+//! deixa o que tinha guardado preso.
+//!
+//! Também mora aqui o [`thread_token`], que o alocador usa pra escolher a
+//! arena de cada thread.
+
+use core::cell::RefCell;
+
+use crate::small::CLASSES;
+
+/// Blocos por magazine (um magazine por classe).
+pub(crate) const MAGAZINE: usize = 32;
+/// Quantos bytes cada thread pode segurar no cache de cada alocador.
+pub(crate) const THREAD_LIMIT: usize = 4 * 1024;
+/// De quantas em quantas operações no cache a thread devolve metade do que guarda.
+pub(crate) const FLUSH_EVERY: usize = 1024;
+// Quantos alocadores com cache uma thread acompanha (o resto passa direto)
+const OWNERS: usize = 4;
+
+/// Devolve um bloco da classe `class` pro alocador `owner`, sem passar pelo
+/// cache. Cada `AlphaAlocator` passa a sua (ver `release_cached`).
+pub(crate) type Release = unsafe fn(owner: *const (), ptr: *mut u8, class: usize);
+
+#[derive(Clone, Copy)]
+struct Magazine {
+    ptrs: [*mut u8; MAGAZINE],
+    len: usize,
+}
+
+impl Magazine {
+    const EMPTY: Magazine = Magazine {
+        ptrs: [core::ptr::null_mut(); MAGAZINE],
+        len: 0,
+    };
+}
+
+/// O cache de uma thread pra um alocador.
+struct Entry {
+    owner: *const (),
+    release: Release,
+    mags: [Magazine; CLASSES.len()],
+    bytes: usize,
+    ops: usize,
+}
+
+impl Entry {
+    /// Devolve os `count` blocos mais antigos do magazine da classe.
+    fn drain(&mut self, class: usize, count: usize) {
+        let mag = &mut self.mags[class];
+        let count = count.min(mag.len);
+        for &ptr in &mag.ptrs[..count] {
+            // Safety: o bloco veio desse dono, com o layout da classe
+            unsafe { (self.release)(self.owner, ptr, class) };
+        }
+        mag.ptrs.copy_within(count..mag.len, 0);
+        mag.len -= count;
+        self.bytes -= count * CLASSES[class];
+    }
+
+    /// Conta uma operação e, de tempos em tempos, devolve metade de tudo.
+    fn tick(&mut self) {
+        self.ops += 1;
+        if self.ops >= FLUSH_EVERY {
+            self.ops = 0;
+            for class in 0..CLASSES.len() {
+                self.drain(class, self.mags[class].len.div_ceil(2));
+            }
+        }
+    }
+}
+
+/// Sem `Drop` (nem nada dentro que tenha), ver o doc do módulo.
+struct ThreadCache {
+    entries: [Option<Entry>; OWNERS],
+    // Já pediu pro `exit` esvaziar esse cache quando a thread terminar
+    armed: bool,
+    // A thread tá terminando e o cache já foi esvaziado: não guarda mais nada
+    closed: bool,
+}
+
+const _: () = assert!(!core::mem::needs_drop::<ThreadCache>());
+
+impl ThreadCache {
+    /// Cache desse dono (pegando uma entrada livre se for a primeira vez).
+    fn entry(&mut self, owner: *const (), release: Release) -> Option<&mut Entry> {
+        if self.closed {
+            return None;
+        }
+        let i = match self.entries.iter().position(|e| e.as_ref().is_some_and(|e| e.owner == owner)) {
+            Some(i) => i,
+            None => {
+                let i = self.entries.iter().position(Option::is_none)?;
+                if !self.armed {
+                    self.armed = exit::arm();
+                }
+                self.entries[i] = Some(Entry {
+                    owner,
+                    release,
+                    mags: [Magazine::EMPTY; CLASSES.len()],
+                    bytes: 0,
+                    ops: 0,
+                });
+                i
+            }
+        };
+        self.entries[i].as_mut()
+    }
+}
+
+std::thread_local! {
+    static CACHE: RefCell<ThreadCache> = const {
+        RefCell::new(ThreadCache {
+            entries: [const { None }; OWNERS],
+            armed: false,
+            closed: false,
+        })
+    };
+}
+
+/// Roda `f` no cache dessa thread pro dono. Devolve None se não der pra usar
+/// o cache agora (thread terminando, chamada reentrante, entradas esgotadas).
+fn with_entry<R>(owner: *const (), release: Release, f: impl FnOnce(&mut Entry) -> R) -> Option<R> {
+    CACHE
+        .try_with(|cache| {
+            let mut cache = cache.try_borrow_mut().ok()?;
+            cache.entry(owner, release).map(f)
+        })
+        .ok()
+        .flatten()
+}
+
+/// Tira um bloco da classe do cache dessa thread, se tiver.
+pub(crate) fn pop(owner: *const (), release: Release, class: usize) -> Option<*mut u8> {
+    with_entry(owner, release, |entry| {
+        entry.tick();
+        let mag = &mut entry.mags[class];
+        if mag.len == 0 {
+            return None;
+        }
+        mag.len -= 1;
+        entry.bytes -= CLASSES[class];
+        Some(mag.ptrs[mag.len])
+    })
+    .flatten()
+}
+
+/// Guarda o bloco no cache dessa thread. Retorna false se não deu (aí quem
+/// chamou solta direto no alocador).
+pub(crate) fn push(owner: *const (), release: Release, class: usize, ptr: *mut u8) -> bool {
+    with_entry(owner, release, |entry| {
+        entry.tick();
+        let size = CLASSES[class];
+        // Cheio (ou passaria do limite da thread): abre espaço devolvendo a
+        // metade mais antiga desse magazine
+        if entry.mags[class].len == MAGAZINE || entry.bytes + size > THREAD_LIMIT {
+            entry.drain(class, MAGAZINE.div_ceil(2));
+        }
+        if entry.bytes + size > THREAD_LIMIT {
+            // Os outros magazines já seguram o limite todo
+            return false;
+        }
+        let mag = &mut entry.mags[class];
+        mag.ptrs[mag.len] = ptr;
+        mag.len += 1;
+        entry.bytes += size;
+        true
+    })
+    .unwrap_or(false)
+}
+
+/// Devolve pro dono tudo que essa thread guarda dele e libera a entrada.
+pub(crate) fn flush(owner: *const ()) {
+    let _ = CACHE.try_with(|cache| {
+        let Ok(mut cache) = cache.try_borrow_mut() else {
+            return;
+        };
+        for slot in cache.entries.iter_mut() {
+            if let Some(entry) = slot.as_mut().filter(|e| e.owner == owner) {
+                for class in 0..CLASSES.len() {
+                    entry.drain(class, MAGAZINE);
+                }
+                *slot = None;
+            }
+        }
+    });
+}
+
+/// Devolve tudo que essa thread guarda (de todos os donos) e fecha o cache:
+/// o que for solto daqui pra frente vai direto pro alocador.
+fn close() {
+    let _ = CACHE.try_with(|cache| {
+        let Ok(mut cache) = cache.try_borrow_mut() else {
+            return;
+        };
+        cache.closed = true;
+        for slot in cache.entries.iter_mut() {
+            if let Some(entry) = slot.as_mut() {
+                for class in 0..CLASSES.len() {
+                    entry.drain(class, MAGAZINE);
+                }
+                *slot = None;
+            }
+        }
+    });
+}
+
+/// Esvazia o cache quando a thread termina, com um destrutor de
+/// `pthread_key_create`. Ele não passa pelo alocador do Rust (no máximo pelo
+/// `malloc` da libc) e roda depois dos destrutores das thread-locals do std
+/// na glibc; se alguma delas soltar algo depois, o cache já tá fechado e o
+/// bloco vai direto.
+#[cfg(unix)]
+mod exit {
+    use core::ffi::{c_int, c_void};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[cfg(target_vendor = "apple")]
+    type Key = core::ffi::c_ulong;
+    #[cfg(not(target_vendor = "apple"))]
+    type Key = core::ffi::c_uint;
+
+    extern "C" {
+        fn pthread_key_create(key: *mut Key, destructor: Option<unsafe extern "C" fn(*mut c_void)>) -> c_int;
+        fn pthread_key_delete(key: Key) -> c_int;
+        fn pthread_setspecific(key: Key, value: *const c_void) -> c_int;
+    }
+
+    // Uma chave pro processo todo, criada no primeiro uso (0 = ainda não, senão chave + 1)
+    static KEY: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn on_exit(_: *mut c_void) {
+        super::close();
+    }
+
+    fn key() -> Option<Key> {
+        let current = KEY.load(Ordering::Acquire);
+        if current != 0 {
+            return Some((current - 1) as Key);
+        }
+        let mut key: Key = 0;
+        if unsafe { pthread_key_create(&mut key, Some(on_exit)) } != 0 {
+            return None;
+        }
+        match KEY.compare_exchange(0, key as usize + 1, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => Some(key),
+            Err(other) => {
+                // Outra thread criou junto: fica com a dela
+                unsafe { pthread_key_delete(key) };
+                Some((other - 1) as Key)
+            }
+        }
+    }
+
+    /// Pede o destrutor pra essa thread. Retorna false se não deu.
+    pub(super) fn arm() -> bool {
+        // O valor só precisa não ser nulo pro destrutor rodar
+        key().is_some_and(|key| unsafe { pthread_setspecific(key, core::ptr::dangling::<c_void>()) } == 0)
+    }
+}
+
+/// Sem jeito de registrar destrutor sem alocar: a thread tem que chamar o `flush`.
+#[cfg(not(unix))]
+mod exit {
+    pub(super) fn arm() -> bool {
+        true
+    }
+}
+
+std::thread_local! {
+    static TOKEN: u8 = const { 0 };
+}
+
+/// Um número diferente pra cada thread viva (o endereço de uma variável da
+/// thread), sem alocar nada como o `thread::current()` pode fazer. Thread
+/// terminando cai no 0.
+pub(crate) fn thread_token() -> usize {
+    TOKEN.try_with(|token| token as *const u8 as usize).unwrap_or(0)
+}
@@ -0,0 +1,84 @@
+//! Avisos que o alocador solta quando vê algo estranho (ex.: `dealloc` de um
+//! ponteiro que não é nosso). Em vez de `eprintln!` direto, tudo passa por um
+//! sink que dá pra trocar, assim funciona sem `std` também.
+
+use core::fmt;
+
+/// Algo estranho que o alocador percebeu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// `dealloc` de um offset que não tem Slot registrado.
+    UnknownSlot { offset: usize },
+    /// `dealloc` de um ponteiro que nem tá dentro da nossa memória.
+    OutsideArena { addr: usize },
+    /// (Modo checado) Não tem bloco vivo em `offset`: já foi solto antes
+    /// (double free) ou nunca foi entregue.
+    DoubleFree { offset: usize },
+    /// (Modo checado) O ponteiro cai no meio do bloco vivo que começa em `block`.
+    InteriorPointer { offset: usize, block: usize },
+    /// (Modo checado) O `layout.size()` (`got`) não bate com o tamanho que o
+    /// bloco tem registrado (`expected`).
+    SizeMismatch { offset: usize, expected: usize, got: usize },
+    /// (Modo checado) O ponteiro não tá alinhado no `layout.align()`.
+    Misaligned { offset: usize, align: usize },
+}
+
+impl Diagnostic {
+    /// O mesmo diagnóstico com os offsets andados `by` bytes (de relativo à
+    /// arena pra relativo à memória inteira).
+    pub(crate) fn shifted(self, by: usize) -> Self {
+        match self {
+            Diagnostic::UnknownSlot { offset } => Diagnostic::UnknownSlot { offset: offset + by },
+            Diagnostic::OutsideArena { addr } => Diagnostic::OutsideArena { addr },
+            Diagnostic::DoubleFree { offset } => Diagnostic::DoubleFree { offset: offset + by },
+            Diagnostic::InteriorPointer { offset, block } => Diagnostic::InteriorPointer {
+                offset: offset + by,
+                block: block + by,
+            },
+            Diagnostic::SizeMismatch { offset, expected, got } => Diagnostic::SizeMismatch {
+                offset: offset + by,
+                expected,
+                got,
+            },
+            Diagnostic::Misaligned { offset, align } => Diagnostic::Misaligned { offset: offset + by, align },
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::UnknownSlot { offset } => {
+                write!(f, "dealloc: não achou slot com offset {}, algo errado!", offset)
+            }
+            Diagnostic::OutsideArena { addr } => {
+                write!(f, "dealloc: ponteiro {:#x} fora da nossa memória, rust pirou!", addr)
+            }
+            Diagnostic::DoubleFree { offset } => {
+                write!(f, "double free (ou ponteiro que nunca foi nosso) no offset {}", offset)
+            }
+            Diagnostic::InteriorPointer { offset, block } => {
+                write!(f, "offset {} é o meio do bloco que começa em {}, não o começo", offset, block)
+            }
+            Diagnostic::SizeMismatch { offset, expected, got } => {
+                write!(f, "bloco em {} tem {} bytes registrados, mas o layout diz {}", offset, expected, got)
+            }
+            Diagnostic::Misaligned { offset, align } => {
+                write!(f, "offset {} não tá alinhado em {} como o layout diz", offset, align)
+            }
+        }
+    }
+}
+
+/// Função que recebe os diagnósticos (ver `AlphaAlocator::set_diagnostic_sink`).
+///
+/// Roda dentro do alocador, então não pode alocar usando ele mesmo.
+pub type DiagnosticSink = fn(&Diagnostic);
+
+/// Sink padrão: com `std` joga no stderr, sem `std` descarta.
+pub fn default_sink(diagnostic: &Diagnostic) {
+    #[cfg(feature = "std")]
+    eprintln!("{}", diagnostic);
+    #[cfg(not(feature = "std"))]
+    let _ = diagnostic;
+}
@@ -0,0 +1,462 @@
+//! Log de eventos: cada `alloc`, `dealloc` e `realloc` vira um registro com
+//! tudo que dá pra saber dele (offset, tamanho, alinhamento, número de ordem e
+//! como terminou), guardado num buffer circular de tamanho fixo. Quando enche,
+//! o evento novo vai por cima do mais antigo.
+//!
+//! # Formato do trace
+//!
+//! O log pode ser exportado como texto (`AlphaAlocator::write_trace`) e lido
+//! de volta linha por linha ([`Event::from_trace`]), por exemplo pelo binário
+//! `replay`. Uma linha por evento, campos separados por espaço:
+//!
+//! ```text
+//! # alocator trace v1
+//! <seq> <op> <size> <align> <offset> <outcome>
+//! ```
+//!
+//! - `op`: `alloc`, `alloc_zeroed`, `dealloc` ou
+//!   `realloc:<offset antigo>:<tamanho antigo>`
+//! - `offset`: em decimal, ou `-` quando o evento não tem offset
+//! - `outcome`: `ok`, `failed:<motivo>` (`oom`, `fragmentation`, `table_full`)
+//!   ou `rejected:<diagnóstico>`, com o diagnóstico em um destes:
+//!   `unknown:<offset>`, `outside:<addr>`, `double_free:<offset>`,
+//!   `interior:<offset>:<block>`, `size:<offset>:<expected>:<got>`,
+//!   `misaligned:<offset>:<align>`
+//!
+//! Linhas vazias ou começando com `#` são ignoradas. Números sempre em decimal.
+
+use core::fmt;
+
+use crate::sync::{Lock, RawLock};
+use crate::{AllocFailure, Diagnostic};
+
+/// Que operação foi pedida.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    Alloc,
+    AllocZeroed,
+    Dealloc,
+    /// O `offset`/`size` do evento são os do bloco depois do `realloc`; os de
+    /// antes ficam aqui.
+    Realloc { old_offset: Option<usize>, old_size: usize },
+}
+
+/// Como a operação terminou.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    /// Devolveu null por falta de espaço.
+    Failed(AllocFailure),
+    /// O ponteiro recebido não passou na conferência: nada foi solto (o mesmo
+    /// diagnóstico também vai pro sink).
+    Rejected(Diagnostic),
+}
+
+/// Um registro do log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// Número de ordem, começando em 0 e sem pular nenhum (mesmo os eventos
+    /// já sobrescritos contam).
+    pub seq: u64,
+    pub kind: EventKind,
+    /// Offset do bloco na memória. None quando não tem bloco: pedido que
+    /// falhou, tamanho zero ou ponteiro de fora da memória.
+    pub offset: Option<usize>,
+    /// Tamanho pedido (não o que o backend reservou). O log guarda em 32
+    /// bits, então pedido acima de `u32::MAX` (que só pode ter falhado) volta
+    /// como `u32::MAX`.
+    pub size: usize,
+    pub align: usize,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} ", self.seq)?;
+        match self.kind {
+            EventKind::Alloc => write!(f, "alloc")?,
+            EventKind::AllocZeroed => write!(f, "alloc_zeroed")?,
+            EventKind::Dealloc => write!(f, "dealloc")?,
+            EventKind::Realloc { old_offset, old_size } => {
+                write!(f, "realloc de {} bytes", old_size)?;
+                if let Some(offset) = old_offset {
+                    write!(f, " em {}", offset)?;
+                }
+                write!(f, " pra")?;
+            }
+        }
+        write!(f, " {} bytes (align {})", self.size, self.align)?;
+        if let Some(offset) = self.offset {
+            write!(f, " em {}", offset)?;
+        }
+        match self.outcome {
+            Outcome::Ok => Ok(()),
+            Outcome::Failed(reason) => write!(f, ": falhou ({:?})", reason),
+            Outcome::Rejected(diagnostic) => write!(f, ": recusado ({})", diagnostic),
+        }
+    }
+}
+
+impl Event {
+    /// Escreve o evento como uma linha do trace (sem o `\n`, ver o formato no
+    /// começo do módulo).
+    pub fn write_trace<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write!(out, "{} ", self.seq)?;
+        match self.kind {
+            EventKind::Alloc => write!(out, "alloc")?,
+            EventKind::AllocZeroed => write!(out, "alloc_zeroed")?,
+            EventKind::Dealloc => write!(out, "dealloc")?,
+            EventKind::Realloc { old_offset, old_size } => {
+                write!(out, "realloc:")?;
+                write_offset(out, old_offset)?;
+                write!(out, ":{}", old_size)?;
+            }
+        }
+        write!(out, " {} {} ", self.size, self.align)?;
+        write_offset(out, self.offset)?;
+        match self.outcome {
+            Outcome::Ok => write!(out, " ok"),
+            Outcome::Failed(reason) => {
+                let name = match reason {
+                    AllocFailure::OutOfMemory => "oom",
+                    AllocFailure::Fragmentation => "fragmentation",
+                    AllocFailure::SlotTableFull => "table_full",
+                };
+                write!(out, " failed:{}", name)
+            }
+            Outcome::Rejected(diagnostic) => match diagnostic {
+                Diagnostic::UnknownSlot { offset } => write!(out, " rejected:unknown:{}", offset),
+                Diagnostic::OutsideArena { addr } => write!(out, " rejected:outside:{}", addr),
+                Diagnostic::DoubleFree { offset } => write!(out, " rejected:double_free:{}", offset),
+                Diagnostic::InteriorPointer { offset, block } => {
+                    write!(out, " rejected:interior:{}:{}", offset, block)
+                }
+                Diagnostic::SizeMismatch { offset, expected, got } => {
+                    write!(out, " rejected:size:{}:{}:{}", offset, expected, got)
+                }
+                Diagnostic::Misaligned { offset, align } => write!(out, " rejected:misaligned:{}:{}", offset, align),
+            },
+        }
+    }
+
+    /// Lê uma linha do trace. `Ok(None)` pra linha vazia ou comentário.
+    pub fn from_trace(line: &str) -> Result<Option<Event>, TraceError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let mut fields = line.split_ascii_whitespace();
+        let mut field = |name: &'static str| fields.next().ok_or(TraceError { field: name });
+
+        let seq = field("seq")?.parse().map_err(|_| TraceError { field: "seq" })?;
+        let op = field("op")?;
+        let kind = match op {
+            "alloc" => EventKind::Alloc,
+            "alloc_zeroed" => EventKind::AllocZeroed,
+            "dealloc" => EventKind::Dealloc,
+            _ => {
+                let mut parts = op.split(':');
+                if parts.next() != Some("realloc") {
+                    return Err(TraceError { field: "op" });
+                }
+                let old_offset = offset(parts.next().unwrap_or(""), "op")?;
+                let old_size = number(parts.next().unwrap_or(""), "op")?;
+                EventKind::Realloc { old_offset, old_size }
+            }
+        };
+        let size = number(field("size")?, "size")?;
+        let align = number(field("align")?, "align")?;
+        let offset = offset(field("offset")?, "offset")?;
+        let outcome = parse_outcome(field("outcome")?).ok_or(TraceError { field: "outcome" })?;
+        if fields.next().is_some() {
+            return Err(TraceError { field: "fim da linha" });
+        }
+        Ok(Some(Event {
+            seq,
+            kind,
+            offset,
+            size,
+            align,
+            outcome,
+        }))
+    }
+}
+
+/// Linha do trace que não deu pra ler: `field` é o campo com problema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceError {
+    pub field: &'static str,
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trace inválido no campo {}", self.field)
+    }
+}
+
+fn write_offset<W: fmt::Write>(out: &mut W, offset: Option<usize>) -> fmt::Result {
+    match offset {
+        Some(offset) => write!(out, "{}", offset),
+        None => write!(out, "-"),
+    }
+}
+
+fn number(text: &str, field: &'static str) -> Result<usize, TraceError> {
+    text.parse().map_err(|_| TraceError { field })
+}
+
+fn offset(text: &str, field: &'static str) -> Result<Option<usize>, TraceError> {
+    if text == "-" {
+        Ok(None)
+    } else {
+        number(text, field).map(Some)
+    }
+}
+
+fn parse_outcome(text: &str) -> Option<Outcome> {
+    let mut parts = text.split(':');
+    let head = parts.next()?;
+    let what = parts.next();
+    let mut args = [0; 3];
+    let mut count = 0;
+    for part in parts {
+        *args.get_mut(count)? = part.parse().ok()?;
+        count += 1;
+    }
+    let outcome = match (head, what, count) {
+        ("ok", None, 0) => Outcome::Ok,
+        ("failed", Some("oom"), 0) => Outcome::Failed(AllocFailure::OutOfMemory),
+        ("failed", Some("fragmentation"), 0) => Outcome::Failed(AllocFailure::Fragmentation),
+        ("failed", Some("table_full"), 0) => Outcome::Failed(AllocFailure::SlotTableFull),
+        ("rejected", Some(what), _) => Outcome::Rejected(match (what, count) {
+            ("unknown", 1) => Diagnostic::UnknownSlot { offset: args[0] },
+            ("outside", 1) => Diagnostic::OutsideArena { addr: args[0] },
+            ("double_free", 1) => Diagnostic::DoubleFree { offset: args[0] },
+            ("interior", 2) => Diagnostic::InteriorPointer {
+                offset: args[0],
+                block: args[1],
+            },
+            ("size", 3) => Diagnostic::SizeMismatch {
+                offset: args[0],
+                expected: args[1],
+                got: args[2],
+            },
+            ("misaligned", 2) => Diagnostic::Misaligned {
+                offset: args[0],
+                align: args[1],
+            },
+            _ => return None,
+        }),
+        _ => return None,
+    };
+    Some(outcome)
+}
+
+// Marca de "sem offset" nos campos de 32 bits do `Record`
+const NO_OFFSET: u32 = u32::MAX;
+
+/// Como o evento fica guardado no buffer: 40 bytes em vez dos 96 do `Event`.
+/// Offsets e tamanhos em 32 bits (a memória é bem menor que isso), o
+/// alinhamento em log2 e o resultado num código com até três argumentos.
+#[derive(Clone, Copy)]
+struct Record {
+    // u64::MAX: lugar vazio
+    seq: u64,
+    offset: u32,
+    size: u32,
+    // Só no `realloc`
+    old_offset: u32,
+    old_size: u32,
+    args: [u32; 3],
+    kind: u8,
+    align_log2: u8,
+    outcome: u8,
+}
+
+const _: () = assert!(core::mem::size_of::<Record>() == 40);
+
+impl Record {
+    const EMPTY: Record = Record {
+        seq: u64::MAX,
+        offset: NO_OFFSET,
+        size: 0,
+        old_offset: NO_OFFSET,
+        old_size: 0,
+        args: [0; 3],
+        kind: 0,
+        align_log2: 0,
+        outcome: 0,
+    };
+
+    fn pack(event: &Event) -> Record {
+        let mut record = Record {
+            seq: event.seq,
+            offset: pack_offset(event.offset),
+            size: narrow(event.size),
+            align_log2: event.align.trailing_zeros() as u8,
+            ..Record::EMPTY
+        };
+        record.kind = match event.kind {
+            EventKind::Alloc => 0,
+            EventKind::AllocZeroed => 1,
+            EventKind::Dealloc => 2,
+            EventKind::Realloc { old_offset, old_size } => {
+                record.old_offset = pack_offset(old_offset);
+                record.old_size = narrow(old_size);
+                3
+            }
+        };
+        let (code, args) = match event.outcome {
+            Outcome::Ok => (0, [0; 3]),
+            Outcome::Failed(AllocFailure::OutOfMemory) => (1, [0; 3]),
+            Outcome::Failed(AllocFailure::Fragmentation) => (2, [0; 3]),
+            Outcome::Failed(AllocFailure::SlotTableFull) => (3, [0; 3]),
+            Outcome::Rejected(diagnostic) => match diagnostic {
+                Diagnostic::UnknownSlot { offset } => (4, [narrow(offset), 0, 0]),
+                // Endereço de fora pode ter 64 bits: vai em duas metades
+                Diagnostic::OutsideArena { addr } => (5, [addr as u32, ((addr as u64) >> 32) as u32, 0]),
+                Diagnostic::DoubleFree { offset } => (6, [narrow(offset), 0, 0]),
+                Diagnostic::InteriorPointer { offset, block } => (7, [narrow(offset), narrow(block), 0]),
+                Diagnostic::SizeMismatch { offset, expected, got } => {
+                    (8, [narrow(offset), narrow(expected), narrow(got)])
+                }
+                Diagnostic::Misaligned { offset, align } => (9, [narrow(offset), align.trailing_zeros(), 0]),
+            },
+        };
+        record.outcome = code;
+        record.args = args;
+        record
+    }
+
+    fn unpack(&self) -> Event {
+        let [a, b, c] = self.args.map(|arg| arg as usize);
+        let outcome = match self.outcome {
+            0 => Outcome::Ok,
+            1 => Outcome::Failed(AllocFailure::OutOfMemory),
+            2 => Outcome::Failed(AllocFailure::Fragmentation),
+            3 => Outcome::Failed(AllocFailure::SlotTableFull),
+            4 => Outcome::Rejected(Diagnostic::UnknownSlot { offset: a }),
+            5 => Outcome::Rejected(Diagnostic::OutsideArena {
+                addr: (self.args[0] as u64 | (self.args[1] as u64) << 32) as usize,
+            }),
+            6 => Outcome::Rejected(Diagnostic::DoubleFree { offset: a }),
+            7 => Outcome::Rejected(Diagnostic::InteriorPointer { offset: a, block: b }),
+            8 => Outcome::Rejected(Diagnostic::SizeMismatch { offset: a, expected: b, got: c }),
+            _ => Outcome::Rejected(Diagnostic::Misaligned { offset: a, align: 1 << b }),
+        };
+        let kind = match self.kind {
+            0 => EventKind::Alloc,
+            1 => EventKind::AllocZeroed,
+            2 => EventKind::Dealloc,
+            _ => EventKind::Realloc {
+                old_offset: unpack_offset(self.old_offset),
+                old_size: self.old_size as usize,
+            },
+        };
+        Event {
+            seq: self.seq,
+            kind,
+            offset: unpack_offset(self.offset),
+            size: self.size as usize,
+            align: 1 << self.align_log2,
+            outcome,
+        }
+    }
+}
+
+fn narrow(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+fn pack_offset(offset: Option<usize>) -> u32 {
+    offset.map_or(NO_OFFSET, narrow)
+}
+
+fn unpack_offset(offset: u32) -> Option<usize> {
+    (offset != NO_OFFSET).then_some(offset as usize)
+}
+
+/// O buffer circular em si (fica atrás de uma trava do alocador).
+pub(crate) struct EventLog<const N: usize> {
+    entries: [Record; N],
+    // `seq` do próximo evento (= quantos já foram registrados)
+    next: u64,
+}
+
+impl<const N: usize> EventLog<N> {
+    pub const fn new() -> Self {
+        EventLog {
+            entries: [Record::EMPTY; N],
+            next: 0,
+        }
+    }
+
+    /// Separa o próximo `seq`. O evento dele vem depois, pelo `push`.
+    pub fn reserve(&mut self) -> u64 {
+        self.next += 1;
+        self.next - 1
+    }
+
+    /// Guarda o evento no lugar do `seq` dele (que veio do `reserve`). Os
+    /// eventos podem chegar fora de ordem: se um mais novo já ocupou o lugar,
+    /// esse já seria sobrescrito mesmo e fica de fora.
+    pub fn push(&mut self, event: Event) {
+        if N == 0 {
+            return;
+        }
+        let entry = &mut self.entries[(event.seq % N as u64) as usize];
+        if entry.seq == u64::MAX || entry.seq < event.seq {
+            *entry = Record::pack(&event);
+        }
+    }
+
+    /// `seq` do evento mais antigo que ainda tá no buffer.
+    fn oldest(&self) -> u64 {
+        self.next.saturating_sub(N as u64)
+    }
+}
+
+/// Iterador pelos eventos do log, do mais antigo pro mais novo (ver
+/// `AlphaAlocator::events`).
+///
+/// Só vai até o último evento que existia quando o iterador foi criado. A
+/// trava do log é pega e solta a cada passo, então dá pra alocar no meio da
+/// iteração; se nesse meio tempo o log der a volta por cima do que ainda ia
+/// ser lido, o iterador pula pro mais antigo que sobrou. Evento que ainda
+/// tava sendo registrado por outra thread (o `seq` já saiu mas o registro
+/// não) fica de fora.
+pub struct Events<'a, L: RawLock, const N: usize> {
+    log: &'a Lock<L, EventLog<N>>,
+    next: u64,
+    end: u64,
+}
+
+impl<'a, L: RawLock, const N: usize> Events<'a, L, N> {
+    pub(crate) fn new(log: &'a Lock<L, EventLog<N>>) -> Self {
+        let guard = log.lock();
+        let (next, end) = (guard.oldest(), guard.next);
+        drop(guard);
+        Events { log, next, end }
+    }
+}
+
+impl<L: RawLock, const N: usize> Iterator for Events<'_, L, N> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        if self.next >= self.end {
+            return None;
+        }
+        let log = self.log.lock();
+        self.next = self.next.max(log.oldest());
+        while self.next < self.end {
+            let seq = self.next;
+            self.next += 1;
+            let record = &log.entries[(seq % N as u64) as usize];
+            if record.seq == seq {
+                return Some(record.unpack());
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,1229 @@
+//! Alocador de memória fixa: uma região estática (`Arena`) e um backend que
+//! decide onde cada bloco (`Slot`) mora, por padrão uma tabela de blocos em uso
+//! ([`SlotTable`]) ou, se preferir, um [`Buddy`] ou um [`Tlsf`]. O crate não instala nada
+//! sozinho, quem quiser usar como alocador global registra com
+//! [`global_alocator!`] ou com o próprio `#[global_allocator]`.
+//!
+//! A feature `std` (ligada por padrão) traz `print_historic`, `print_map`, diagnósticos no
+//! stderr e o cache por thread (`with_thread_cache`). Sem ela o crate é `no_std`. A trava usada por dentro
+//! é escolhida pelo parâmetro de tipo `L` (ver [`RawLock`]).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod backend;
+mod buddy;
+#[cfg(feature = "std")]
+mod cache;
+mod diagnostic;
+mod events;
+mod map;
+mod placement;
+mod slots;
+mod small;
+mod stats;
+mod sync;
+mod tlsf;
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub use backend::{Backend, Block, Region};
+pub use buddy::Buddy;
+pub use diagnostic::{default_sink, Diagnostic, DiagnosticSink};
+use events::EventLog;
+pub use map::{MAP_FREE, MAP_OVERHEAD, MAP_USED};
+pub use events::{Event, EventKind, Events, Outcome, TraceError};
+pub use placement::PlacementPolicy;
+pub use slots::{Slot, SlotTable};
+use small::SmallHeap;
+use stats::Counters;
+pub use stats::{FailureCounts, FailureSnapshot, Fragmentation, Stats, GAP_BUCKETS};
+#[cfg(feature = "critical-section")]
+pub use sync::CriticalSectionLock;
+pub use sync::{RawLock, SpinLock};
+pub use tlsf::Tlsf;
+use sync::Lock;
+
+// Tamanhos padrão, usados quando ninguém escolhe os parâmetros do AlphaAlocator
+pub const MEMORY_SIZE: usize = 30000;
+pub const HISTORIC_SIZE: usize = 400;
+
+/// A região de memória que o alocador distribui.
+///
+/// Os bytes ficam dentro de um `UnsafeCell`, então dá pra escrever neles através
+/// de um `&Arena` (e o `static` vai pra uma seção gravável, não pra `.rodata`).
+///
+/// Soundness: o `Arena` nunca lê nem escreve nos bytes sozinho, ele só entrega
+/// ponteiros crus via [`Arena::base`]. Quem garante que dois ponteiros entregues
+/// não se sobrepõem é o `AlphaAlocator` (via o backend), e cada faixa
+/// `[offset, offset + size)` pertence exclusivamente a quem recebeu o ponteiro
+/// até o `dealloc`. Nenhuma referência `&[u8]`/`&mut [u8]` pra região inteira é
+/// criada, só aritmética de ponteiro, então os acessos dos usuários não brigam
+/// com referências do alocador.
+///
+/// O começo fica alinhado em 16, então tudo que os backends alinham relativo ao
+/// início (tipo os blocos do [`Buddy`]) também fica alinhado de verdade.
+#[repr(C, align(16))]
+pub struct Arena<const MEM: usize = MEMORY_SIZE> {
+    bytes: UnsafeCell<[u8; MEM]>,
+}
+
+// Safety: o acesso aos bytes é só por ponteiro cru, e a exclusividade de cada
+// faixa é garantida pelo `AlphaAlocator` (ver doc do tipo).
+unsafe impl<const MEM: usize> Sync for Arena<MEM> {}
+
+impl<const MEM: usize> Arena<MEM> {
+    pub const fn new() -> Self {
+        Arena {
+            bytes: UnsafeCell::new([0; MEM]),
+        }
+    }
+
+    /// Ponteiro pro primeiro byte da região (com permissão de escrita).
+    pub fn base(&self) -> *mut u8 {
+        self.bytes.get() as *mut u8
+    }
+
+    /// Tamanho da região em bytes.
+    pub const fn len(&self) -> usize {
+        MEM
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        MEM == 0
+    }
+}
+
+impl<const MEM: usize> Default for Arena<MEM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Motivo de um `alloc` ter devolvido null.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocFailure {
+    /// Não sobrou `free` suficiente nem somando tudo.
+    OutOfMemory,
+    /// Tem `free` no total, mas nenhum buraco contíguo (e alinhado) cabe o pedido.
+    Fragmentation,
+    /// Tem buraco, mas a [`SlotTable`] não tem mais onde crescer pra registrar o
+    /// bloco (a tabela mora no topo da memória, colada no último bloco).
+    SlotTableFull,
+}
+
+impl AllocFailure {
+    const fn code(self) -> usize {
+        match self {
+            AllocFailure::OutOfMemory => 1,
+            AllocFailure::Fragmentation => 2,
+            AllocFailure::SlotTableFull => 3,
+        }
+    }
+
+    const fn from_code(code: usize) -> Option<Self> {
+        match code {
+            1 => Some(AllocFailure::OutOfMemory),
+            2 => Some(AllocFailure::Fragmentation),
+            3 => Some(AllocFailure::SlotTableFull),
+            _ => None,
+        }
+    }
+}
+
+/// O que a trava de cada arena protege: o backend, quanto da arena tá ocupado
+/// e o high water mark. Tudo muda junto, então achar o buraco, registrar o
+/// bloco e atualizar as contas é uma seção crítica só (nada de conferir o
+/// espaço livre de um lado e atualizar do outro).
+struct Heap<B> {
+    backend: B,
+    // Bytes ocupados: o que o backend reservou pros blocos mais os metadados dele
+    used: usize,
+    // Maior offset (fim de bloco, relativo à arena) que já foi entregue alguma
+    // vez. Tudo daqui pra frente nunca foi tocado, então continua zerado.
+    high_water: usize,
+}
+
+/// Onde a operação tá com o `seq` dela no log. O `seq` é pego com a trava
+/// da arena pega, no passo que entrega ou solta o bloco: pego só no fim, o
+/// `dealloc` de uma thread podia sair no log depois do `alloc` de outra que
+/// já tinha pegado aquele mesmo lugar.
+#[derive(Clone, Copy)]
+enum Stamp {
+    /// Ainda não pegou (se nenhum passo pegar, pega no fim).
+    Pending,
+    /// Passo do meio, que não é o evento (run novo, bloco saindo do cache).
+    Skip,
+    Taken(u64),
+}
+
+/// Uma das arenas: um pedaço da memória com backend, classes pequenas e
+/// travas próprios, então threads em arenas diferentes não disputam nada.
+struct Shard<L: RawLock, B> {
+    heap: Lock<L, Heap<B>>,
+    // Free lists das classes pequenas (só usadas com `size_classes` ligado)
+    small: Lock<L, SmallHeap>,
+}
+
+/// Alocador de memória fixa.
+///
+/// - `MEM`: tamanho da região (em bytes, no máximo `u32::MAX`: os backends
+///   guardam offsets e tamanhos em u32)
+/// - `HIST`: quantos eventos o log guarda (ver [`events`](Self::events)). O
+///   log é um só pro alocador inteiro, com trava própria; com `HIST = 0` ele
+///   some e nenhum pedido passa por essa trava (vale pra quem usa o cache por
+///   thread ou várias arenas pra não disputar trava)
+/// - `L`: a trava que protege as tabelas ([`SpinLock`] por padrão,
+///   ou [`CriticalSectionLock`] com a feature `critical-section`)
+/// - `B`: o backend que organiza os blocos ([`SlotTable`] por padrão, [`Buddy`]
+///   ou [`Tlsf`] quando precisa de tempo constante)
+/// - `ARENAS`: em quantas arenas a memória é dividida (1 por padrão). Cada
+///   arena é um pedaço contíguo da memória com backend e trava próprios; cada
+///   thread tem a sua arena preferida (pelo hash da thread, com `std`) e só
+///   vai pras outras quando ela não dá conta. O `dealloc` acha a arena certa
+///   pelo endereço. Dá pra escolher a arena na mão com [`alloc_in`](Self::alloc_in).
+///
+/// Cada alvo escolhe os seus no `#[global_allocator]`, por exemplo
+/// `static A: AlphaAlocator<8192, 0> = AlphaAlocator::new();`
+/// (ou via [`global_alocator!`]).
+pub struct AlphaAlocator<
+    const MEM: usize = MEMORY_SIZE,
+    const HIST: usize = HISTORIC_SIZE,
+    L: RawLock = SpinLock,
+    B: Backend = SlotTable,
+    const ARENAS: usize = 1,
+> {
+    memory: Arena<MEM>, // memória
+    arenas: [Shard<L, B>; ARENAS],
+    events: Lock<L, EventLog<HIST>>,
+    last_failure: AtomicUsize, // AllocFailure::code() da última falha (0 = nenhuma)
+    counters: Counters,
+    // Fragmentação capturada na última falha (só com `failure_snapshots`, que
+    // vem ligado nos backends que não escrevem na memória livre)
+    failure_snapshot: Lock<L, Option<FailureSnapshot>>,
+    failure_snapshots: bool,
+    // Soma do `used` de todas as arenas e o maior valor que ela já teve. Só
+    // mudam dentro do `with_heap` (com a trava de alguma arena pega)
+    used_total: AtomicUsize,
+    peak: AtomicUsize,
+    diagnostic_sink: Lock<L, DiagnosticSink>,
+    policy: PlacementPolicy,
+    size_classes: bool,
+    // Modo checado: `dealloc`/`realloc` conferem o ponteiro antes de soltar
+    checked: bool,
+    // Cache por thread na frente de tudo (só com `std`)
+    thread_cache: bool,
+    // Quantos bytes da memória são usados de verdade (`MEM`, ou o `with_memory_limit`)
+    len: usize,
+}
+
+impl<const MEM: usize, const HIST: usize, L: RawLock, B: Backend, const ARENAS: usize>
+    AlphaAlocator<MEM, HIST, L, B, ARENAS>
+{
+    // Erro de compilação (e não offset truncado em silêncio) se a memória não
+    // couber nos u32 da tabela de slots, dos bitmaps do buddy e do TLSF
+    const MEM_FITS_U32: () = assert!(MEM as u64 <= u32::MAX as u64, "MEM passa de u32::MAX");
+
+    pub const fn new() -> Self {
+        Self::with_policy(PlacementPolicy::FirstFit)
+    }
+
+    /// Igual o `new`, mas escolhendo como os buracos são escolhidos
+    /// (só o backend [`SlotTable`] usa isso).
+    pub const fn with_policy(policy: PlacementPolicy) -> Self {
+        let () = Self::MEM_FITS_U32;
+        assert!(ARENAS == 1 || (ARENAS > 0 && MEM / ARENAS >= 16), "arenas demais pra essa memória");
+        AlphaAlocator {
+            memory: Arena::new(),
+            arenas: [const {
+                Shard {
+                    heap: Lock::new(Heap {
+                        backend: B::INIT,
+                        used: 0,
+                        high_water: 0,
+                    }),
+                    small: Lock::new(SmallHeap::new()),
+                }
+            }; ARENAS],
+            events: Lock::new(EventLog::new()),
+            last_failure: AtomicUsize::new(0),
+            counters: Counters::new(),
+            failure_snapshot: Lock::new(None),
+            failure_snapshots: B::KEEPS_FREE_MEMORY_CLEAN,
+            used_total: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            diagnostic_sink: Lock::new(default_sink),
+            policy,
+            size_classes: false,
+            checked: false,
+            thread_cache: false,
+            len: MEM,
+        }
+    }
+
+    /// Usa só os primeiros `bytes` da memória; o resto fica parado. Fora isso
+    /// o alocador se comporta igual a um com `MEM = bytes`, então serve pra
+    /// testar vários tamanhos sem compilar um alocador pra cada (é o que o
+    /// binário `replay` faz).
+    pub const fn with_memory_limit(mut self, bytes: usize) -> Self {
+        assert!(bytes <= MEM, "limite maior que a memória");
+        assert!(ARENAS == 1 || bytes / ARENAS >= 16, "arenas demais pra essa memória");
+        self.len = bytes;
+        self
+    }
+
+    /// Liga as classes de tamanho pra pedidos pequenos (até 128 bytes).
+    ///
+    /// Esses pedidos passam a sair de free lists O(1), em runs de 512 bytes
+    /// pegos da memória, em vez de passar pelo backend toda vez. Run que fica
+    /// todo livre volta pra memória geral, menos o último de cada classe.
+    pub const fn with_size_classes(mut self) -> Self {
+        self.size_classes = true;
+        self
+    }
+
+    /// Liga o modo checado: antes de soltar (ou mudar de tamanho) um bloco, o
+    /// `dealloc` e o `realloc` conferem se o ponteiro é mesmo o começo de um
+    /// bloco vivo e se o layout bate com o tamanho registrado e com o
+    /// alinhamento do ponteiro. Se não bater, nada é solto e o problema vai
+    /// pro sink de diagnóstico ([`Diagnostic::DoubleFree`],
+    /// [`Diagnostic::InteriorPointer`], [`Diagnostic::SizeMismatch`],
+    /// [`Diagnostic::Misaligned`]).
+    ///
+    /// Custa caro: dependendo do backend a conferência anda por todos os blocos.
+    pub const fn with_checks(mut self) -> Self {
+        self.checked = true;
+        self
+    }
+
+    /// Liga a foto da memória a cada falha (ver
+    /// [`last_failure_snapshot`](Self::last_failure_snapshot)). Com a
+    /// [`SlotTable`] ela já vem ligada.
+    ///
+    /// Custa caro justo no `alloc` que falhou: anda por todos os buracos de
+    /// cada arena tentada, o que estraga o tempo garantido do TLSF e do buddy.
+    /// Bom pra depurar e dimensionar, não pra produção.
+    pub const fn with_failure_snapshots(mut self) -> Self {
+        self.failure_snapshots = true;
+        self
+    }
+
+    /// Desliga a foto da memória a cada falha (o motivo continua em
+    /// [`last_failure`](Self::last_failure) e nos contadores).
+    pub const fn without_failure_snapshots(mut self) -> Self {
+        self.failure_snapshots = false;
+        self
+    }
+
+    /// Liga o cache por thread pros pedidos pequenos (até 128 bytes, nas
+    /// mesmas classes do [`with_size_classes`](Self::with_size_classes)).
+    ///
+    /// Cada thread guarda os blocos pequenos que soltou e reaproveita nos
+    /// próximos pedidos da mesma classe sem pegar a trava do alocador. Cada
+    /// thread segura no máximo 4 KiB por alocador e, de tempos em tempos, os
+    /// blocos guardados voltam pro alocador. No unix tudo volta também
+    /// quando a thread termina (por um destrutor de `pthread_key_create`, que
+    /// não aloca pelo alocador do Rust). Nas outras plataformas registrar o
+    /// destrutor de uma thread-local aloca, então lá a thread tem que chamar
+    /// [`flush_thread_cache`](Self::flush_thread_cache) antes de terminar,
+    /// senão o que ela guardava fica preso.
+    /// Pra caber em qualquer pedido da classe, os blocos pequenos passam a
+    /// ser reservados com o tamanho da classe inteira. No modo checado o
+    /// cache é ignorado (senão ele esconderia double free).
+    ///
+    /// # Safety
+    ///
+    /// As threads guardam o endereço do alocador e devolvem os blocos pra ele
+    /// mais tarde, então o alocador tem que continuar vivo (e no mesmo lugar)
+    /// até a última thread que usou ele terminar. Um `static` serve.
+    #[cfg(feature = "std")]
+    pub const unsafe fn with_thread_cache(mut self) -> Self {
+        self.thread_cache = true;
+        self
+    }
+
+    /// Devolve pro alocador todos os blocos que o cache dessa thread guarda
+    /// dele (ver [`with_thread_cache`](Self::with_thread_cache)). Sem cache
+    /// ligado não faz nada.
+    #[cfg(feature = "std")]
+    pub fn flush_thread_cache(&self) {
+        if self.thread_cache {
+            cache::flush(self as *const Self as *const ());
+        }
+    }
+
+    /// Política de escolha de buraco usada por esse alocador.
+    pub fn policy(&self) -> PlacementPolicy {
+        self.policy
+    }
+
+    /// Troca pra onde vão os diagnósticos (por padrão, [`default_sink`]).
+    pub fn set_diagnostic_sink(&self, sink: DiagnosticSink) {
+        *self.diagnostic_sink.lock() = sink;
+    }
+
+    fn report(&self, diagnostic: Diagnostic) {
+        let sink = *self.diagnostic_sink.lock();
+        sink(&diagnostic);
+    }
+
+    /// Motivo da última vez que o `alloc` devolveu null (None se nunca falhou).
+    pub fn last_failure(&self) -> Option<AllocFailure> {
+        AllocFailure::from_code(self.last_failure.load(Ordering::SeqCst))
+    }
+
+    /// O que foi guardado da última vez que o `alloc` devolveu null: motivo,
+    /// layout pedido e a [`Fragmentation`] das arenas tentadas naquela hora.
+    /// Sempre None com a foto desligada (ver
+    /// [`with_failure_snapshots`](Self::with_failure_snapshots)).
+    pub fn last_failure_snapshot(&self) -> Option<FailureSnapshot> {
+        *self.failure_snapshot.lock()
+    }
+
+    /// Guarda o motivo da falha (o `alloc` vai devolver null) e, se a foto
+    /// estiver ligada, como a memória tava picotada. Os buracos de cada arena
+    /// foram contados no `alloc_gap`, ainda com a trava dela pega.
+    fn fail(&self, reason: AllocFailure, layout: Layout, fragmentation: Fragmentation) {
+        self.last_failure.store(reason.code(), Ordering::SeqCst);
+        self.counters.fail(reason);
+        if !self.failure_snapshots {
+            return;
+        }
+        *self.failure_snapshot.lock() = Some(FailureSnapshot {
+            reason,
+            size: layout.size(),
+            align: layout.align(),
+            fragmentation,
+        });
+    }
+
+    /// Foto do uso da memória: bytes vivos, pico, chamadas, falhas por motivo,
+    /// blocos vivos e o maior buraco livre (ver [`Stats`]).
+    ///
+    /// Pega a trava de todas as arenas ao mesmo tempo e anda por todos os
+    /// blocos e buracos, então não é de graça.
+    pub fn stats(&self) -> Stats {
+        let heaps: [_; ARENAS] = core::array::from_fn(|i| self.arenas[i].heap.lock());
+        let mut stats = Stats::default();
+        for (i, heap) in heaps.iter().enumerate() {
+            let (_, region) = self.shard_region(i);
+            let metadata = heap.backend.metadata_bytes(region);
+            stats.metadata_bytes += metadata;
+            stats.live_bytes += heap.used - metadata;
+            heap.backend.for_each_block(region, &mut |_| stats.live_blocks += 1);
+            heap.backend.for_each_gap(region, &mut |start, end| {
+                stats.largest_free_gap = stats.largest_free_gap.max(end - start);
+            });
+        }
+        // Solta na ordem contrária da que pegou: o `CriticalSectionLock` só
+        // aceita seções aninhadas saindo da mais de dentro pra fora
+        for heap in heaps.into_iter().rev() {
+            drop(heap);
+        }
+        stats.peak_bytes = self.peak.load(Ordering::Relaxed);
+        self.counters.fill(&mut stats);
+        stats
+    }
+
+    /// Registra um evento no log. O offset sai do ponteiro (null, `dangling`
+    /// ou ponteiro de fora ficam sem offset).
+    fn record(&self, kind: EventKind, ptr: *mut u8, layout: Layout, outcome: Outcome, stamp: Stamp) {
+        if HIST == 0 {
+            return;
+        }
+        let offset = self.identify_adress(ptr);
+        let mut log = self.events.lock();
+        let seq = match stamp {
+            Stamp::Taken(seq) => seq,
+            // Nenhum passo mexeu na memória compartilhada: a ordem é a de agora
+            _ => log.reserve(),
+        };
+        log.push(Event {
+            seq,
+            kind,
+            offset,
+            size: layout.size(),
+            align: layout.align(),
+            outcome,
+        });
+    }
+
+    /// Pega o `seq` da operação, se ela ainda não tem. Chamado com a trava da
+    /// arena pega, no passo que entrega ou solta o bloco.
+    fn stamp(&self, stamp: &mut Stamp) {
+        if HIST > 0 && matches!(stamp, Stamp::Pending) {
+            *stamp = Stamp::Taken(self.events.lock().reserve());
+        }
+    }
+
+    /// Eventos do log, do mais antigo pro mais novo (só os últimos `HIST`).
+    ///
+    /// Cada `alloc`, `alloc_zeroed`, `dealloc`, `realloc` e [`alloc_in`](Self::alloc_in)
+    /// vira um evento só, inclusive os resolvidos no cache por thread (um
+    /// `realloc` que muda o bloco de lugar não aparece como alloc + dealloc).
+    /// Os blocos que o cache devolve sozinho pro alocador não aparecem.
+    ///
+    /// A ordem do `seq` é a ordem em que as operações mexeram na memória,
+    /// mesmo entre threads: o bloco solto por uma thread só aparece alocado
+    /// por outra depois do `dealloc` dele.
+    pub fn events(&self) -> Events<'_, L, HIST> {
+        Events::new(&self.events)
+    }
+
+    /// Escreve o log de eventos em qualquer `fmt::Write` (serve sem `std`)
+    pub fn write_historic<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        // O iterador não segura a trava entre um evento e outro: se o `out`
+        // alocar (ou der panic e o hook alocar) o `alloc` consegue registrar
+        writeln!(out, "\n\nHistoric of allocations\n\n")?;
+        for event in self.events() {
+            writeln!(out, "{}", event)?;
+        }
+        Ok(())
+    }
+
+    /// Exporta o log de eventos no formato de trace (documentado no módulo
+    /// `events`, e lido de volta com [`Event::from_trace`]), um evento por
+    /// linha, pra estudar depois ou rodar de novo com o binário `replay`.
+    pub fn write_trace<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        writeln!(out, "# alocator trace v1")?;
+        writeln!(out, "# mem {} arenas {} policy {:?}", self.len, ARENAS, self.policy)?;
+        for event in self.events() {
+            event.write_trace(out)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Printa o log de eventos, caso quisermos depurar
+    #[cfg(feature = "std")]
+    pub fn print_historic(&self) {
+        let _ = self.write_historic(&mut Stdout);
+    }
+
+    /// Desenha a memória em qualquer `fmt::Write` (serve sem `std`): um mapa
+    /// com um caractere a cada `bytes_per_char` bytes ([`MAP_USED`] pedido,
+    /// [`MAP_FREE`] livre, [`MAP_OVERHEAD`] metadados/cabeçalho/sobra; cada
+    /// caractere mostra o que ocupa mais naquele pedaço) e depois a tabela
+    /// dos blocos vivos em ordem de offset, com quanto de cada um é sobra.
+    ///
+    /// Nada é escrito com trava pega (o `out` pode alocar), então cada leva de
+    /// linhas do mapa e cada leva da tabela é lida separada: com outras threads
+    /// mexendo, uma leva pode não bater com a outra.
+    pub fn dump_map<W: fmt::Write>(&self, out: &mut W, bytes_per_char: usize) -> fmt::Result {
+        let cell = bytes_per_char.max(1);
+        let line = cell.saturating_mul(map::LINE);
+        writeln!(out, "Mapa da memória: {} bytes em {} arena(s), 1 caractere = {} bytes", self.len, ARENAS, cell)?;
+        writeln!(out, "{} pedido, {} livre, {} metadados/cabeçalho/sobra\n", MAP_USED, MAP_FREE, MAP_OVERHEAD)?;
+        // Uma passada pelos blocos e buracos a cada `map::BATCH` linhas
+        let mut first = 0;
+        while first < self.len {
+            let (mut used, mut free) = ([0; map::LINE * map::BATCH], [0; map::LINE * map::BATCH]);
+            self.for_each_block(|_, req| map::add_range(&mut used, first, cell, req.index, req.index + req.size));
+            self.for_each_gap(|start, end| map::add_range(&mut free, first, cell, start, end));
+
+            let mut line_start = first;
+            for _ in 0..map::BATCH {
+                if line_start >= self.len {
+                    break;
+                }
+                let line_end = self.len.min(line_start.saturating_add(line));
+                write!(out, "{:>8} ", line_start)?;
+                let mut pos = line_start;
+                while pos < line_end {
+                    let (c, len) = ((pos - first) / cell, cell.min(line_end - pos));
+                    out.write_char(map::cell_char(len, used[c], free[c]))?;
+                    pos += len;
+                }
+                writeln!(out)?;
+                line_start = line_end;
+            }
+            first = line_start;
+        }
+
+        writeln!(out, "\n{:>8} {:>8} {:>8} {:>8} {:>6}", "offset", "tamanho", "fim", "sobra", "arena")?;
+        // Lê de 32 em 32 blocos (a partir do offset `next`) e escreve com a trava solta
+        const BATCH: usize = 32;
+        let (mut next, mut total) = (0, 0);
+        loop {
+            let mut batch = [(0, Slot { size: 0, index: 0 }); BATCH];
+            let mut n = 0;
+            self.for_each_block(|slot, req| {
+                if slot.index >= next && n < BATCH {
+                    batch[n] = (slot.size - req.size, slot);
+                    n += 1;
+                }
+            });
+            for &(slack, slot) in &batch[..n] {
+                let arena = self.shard_of(slot.index);
+                let end = slot.index + slot.size;
+                writeln!(out, "{:>8} {:>8} {:>8} {:>8} {:>6}", slot.index, slot.size, end, slack, arena)?;
+            }
+            total += n;
+            if n < BATCH {
+                break;
+            }
+            next = batch[n - 1].1.index + 1;
+        }
+        writeln!(out, "{} bloco(s) vivo(s)", total)
+    }
+
+    /// Printa o mapa da memória (ver [`dump_map`](Self::dump_map)).
+    #[cfg(feature = "std")]
+    pub fn print_map(&self, bytes_per_char: usize) {
+        let _ = self.dump_map(&mut Stdout, bytes_per_char);
+    }
+
+    /// Chama `f` pra cada bloco em uso, em ordem de offset (com a trava da
+    /// arena pega, então `f` não pode alocar usando esse mesmo alocador).
+    pub fn for_each_slot<F: FnMut(Slot)>(&self, mut f: F) {
+        self.for_each_block(|slot, _| f(slot));
+    }
+
+    /// Igual o [`for_each_slot`](Self::for_each_slot), mas passando junto o
+    /// pedaço do bloco que o pedido ocupa (ver [`Backend::requested`]).
+    fn for_each_block<F: FnMut(Slot, Slot)>(&self, mut f: F) {
+        for (i, shard) in self.arenas.iter().enumerate() {
+            let (start, region) = self.shard_region(i);
+            let heap = shard.heap.lock();
+            heap.backend.for_each_block(region, &mut |slot| {
+                let req = heap.backend.requested(region, slot);
+                f(
+                    Slot { size: slot.size, index: start + slot.index },
+                    Slot { size: req.size, index: start + req.index },
+                )
+            });
+        }
+    }
+
+    /// Chama `f(início, fim)` pra cada buraco livre, em ordem de offset (com a
+    /// trava da arena pega, igual o [`for_each_slot`](Self::for_each_slot)).
+    /// Buracos de arenas diferentes aparecem separados mesmo se encostarem,
+    /// porque nenhum pedido usa duas arenas.
+    pub fn for_each_gap<F: FnMut(usize, usize)>(&self, mut f: F) {
+        for (i, shard) in self.arenas.iter().enumerate() {
+            let (start, region) = self.shard_region(i);
+            shard
+                .heap
+                .lock()
+                .backend
+                .for_each_gap(region, &mut |gap_start, gap_end| f(start + gap_start, start + gap_end));
+        }
+    }
+
+    /// Quanto a memória livre tá picotada agora: livre total, número de
+    /// buracos, o maior, histograma de tamanhos e a razão de fragmentação
+    /// externa (ver [`Fragmentation`]).
+    pub fn fragmentation(&self) -> Fragmentation {
+        let mut fragmentation = Fragmentation::new();
+        self.for_each_gap(|start, end| fragmentation.add(end - start));
+        fragmentation
+    }
+
+    /// Em quantas arenas a memória tá dividida.
+    pub const fn arenas(&self) -> usize {
+        ARENAS
+    }
+
+    /// Arena dona do ponteiro (None se ele nem é da nossa memória).
+    pub fn arena_of(&self, ptr: *mut u8) -> Option<usize> {
+        self.identify_adress(ptr).map(|offset| self.shard_of(offset))
+    }
+
+    /// Tamanho de cada arena, múltiplo de 16 pra cada uma começar tão alinhada
+    /// quanto a memória (a última fica com a sobra da divisão).
+    fn shard_len(&self) -> usize {
+        if ARENAS == 1 {
+            self.len
+        } else {
+            (self.len / ARENAS) & !15
+        }
+    }
+
+    /// Onde a arena `i` começa (offset na memória) e a região dela.
+    fn shard_region(&self, i: usize) -> (usize, Region) {
+        let start = i * self.shard_len();
+        let end = if i + 1 == ARENAS { self.len } else { start + self.shard_len() };
+        // Safety: start <= len <= MEM
+        let base = unsafe { self.memory.base().add(start) };
+        (start, Region::new(base, end - start))
+    }
+
+    /// Arena que contém o `offset`.
+    fn shard_of(&self, offset: usize) -> usize {
+        if ARENAS == 1 {
+            0
+        } else {
+            (offset / self.shard_len()).min(ARENAS - 1)
+        }
+    }
+
+    /// Arena preferida da thread atual: hash da thread com `std`, senão a 0.
+    fn home_shard(&self) -> usize {
+        if ARENAS == 1 {
+            return 0;
+        }
+        #[cfg(feature = "std")]
+        {
+            // Hash de Fibonacci (os bits de cima do produto são os bem
+            // misturados): os tokens de threads vizinhas são quase iguais
+            let hash = (cache::thread_token() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 48;
+            hash as usize % ARENAS
+        }
+        #[cfg(not(feature = "std"))]
+        0
+    }
+
+    /// Roda `f` com a trava da arena `i` pega e, ainda dentro dela, soma no
+    /// `used` o que os metadados do backend cresceram (ou tira o que
+    /// encolheram). Tudo que mexe em bloco ou nas contas passa por aqui, então
+    /// é aqui também que o total de todas as arenas e o pico são atualizados.
+    fn with_heap<R>(&self, i: usize, f: impl FnOnce(&mut Heap<B>, Region) -> R) -> R {
+        let (_, region) = self.shard_region(i);
+        let mut heap = self.arenas[i].heap.lock();
+        let (used, metadata) = (heap.used, heap.backend.metadata_bytes(region));
+        let result = f(&mut heap, region);
+        heap.used = heap.used + heap.backend.metadata_bytes(region) - metadata;
+        if heap.used > used {
+            let grew = heap.used - used;
+            let total = self.used_total.fetch_add(grew, Ordering::Relaxed) + grew;
+            self.peak.fetch_max(total, Ordering::Relaxed);
+        } else {
+            self.used_total.fetch_sub(used - heap.used, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Identifica o offset (index do Slot) correspondente ao ponteiro
+    pub fn identify_adress(&self, ptr: *mut u8) -> Option<usize> {
+        let base = self.memory.base() as usize; // endereço do início
+        let alvo = ptr as usize;
+        if alvo < base {
+            return None;
+        }
+        let offset = alvo - base;
+        if offset < self.len {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+
+    /// Faz o trabalho do `alloc`: acha o buraco, registra o Slot e ajusta as
+    /// contas. Tenta a arena preferida da thread e depois as outras (ou só a
+    /// arena `only`, se vier). Retorna o offset do bloco e o high water mark de
+    /// antes dessa alocação (bytes a partir dele nunca foram entregues pra ninguém).
+    fn alloc_block(&self, layout: Layout, only: Option<usize>, stamp: &mut Stamp) -> Result<(usize, usize), AllocFailure> {
+        let (first, count) = match only {
+            Some(i) => (i, 1),
+            None => (self.home_shard(), ARENAS),
+        };
+        let mut failure = None;
+        let mut fragmentation = Fragmentation::new();
+        for i in (0..count).map(|k| (first + k) % ARENAS) {
+            // Pedido pequeno: tenta a célula antes de varrer a tabela
+            if let Some(class) = self.small_class(layout) {
+                if let Some(offset) = self.alloc_cell(i, class, stamp) {
+                    // Célula reaproveitada (ou com o link da free list) pode estar
+                    // suja, então pro `alloc_zeroed` ela conta como toda suja
+                    return Ok((offset, usize::MAX));
+                }
+                // Sem célula nem espaço pra run novo: cai na varredura normal
+            }
+
+            match self.alloc_gap(i, layout, Some(&mut fragmentation), stamp) {
+                Ok(block) => return Ok(block),
+                // O motivo que fica é o da primeira arena tentada
+                Err(reason) => {
+                    failure.get_or_insert(reason);
+                }
+            }
+        }
+        // Sempre tem motivo: toda arena tentada passou pelo `alloc_gap`
+        let reason = failure.unwrap_or(AllocFailure::OutOfMemory);
+        self.fail(reason, layout, fragmentation);
+        Err(reason)
+    }
+
+    /// Aloca pelo backend da arena `i` (o caminho normal), ajustando as contas
+    /// e o high water mark. Offsets já voltam relativos à memória inteira. Se
+    /// falhar com a foto ligada, os buracos da arena vão pro `snapshot`.
+    fn alloc_gap(
+        &self,
+        i: usize,
+        layout: Layout,
+        snapshot: Option<&mut Fragmentation>,
+        stamp: &mut Stamp,
+    ) -> Result<(usize, usize), AllocFailure> {
+        let size = layout.size();
+        let (start, _) = self.shard_region(i);
+        let (offset, dirty_end) = self.with_heap(i, |heap, region| {
+            // O padding do alinhamento continua livre (fica no buraco antes do
+            // bloco), então só o que o backend reservou conta como ocupado.
+            let block = if size > region.len() - heap.used {
+                // Sem espaço total: devolve null e deixa o `handle_alloc_error` decidir
+                Err(AllocFailure::OutOfMemory)
+            } else {
+                heap.backend.alloc(region, layout, self.policy)
+            };
+            let block = match block {
+                Ok(block) => block,
+                Err(reason) => {
+                    // Ainda com a trava: a foto é da memória que fez o pedido falhar
+                    if let Some(snapshot) = snapshot.filter(|_| self.failure_snapshots) {
+                        heap.backend.for_each_gap(region, &mut |gap_start, gap_end| snapshot.add(gap_end - gap_start));
+                    }
+                    return Err(reason);
+                }
+            };
+            heap.used += block.reserved;
+            // Sobe o high water mark se esse bloco passou do ponto mais alto
+            let dirty_end = heap.high_water;
+            heap.high_water = heap.high_water.max(block.offset + size);
+            self.stamp(stamp);
+            Ok((block.offset, dirty_end))
+        })?;
+
+        if B::KEEPS_FREE_MEMORY_CLEAN {
+            Ok((start + offset, start + dirty_end))
+        } else {
+            // Backend escreve na memória livre: nada garante que tá zerado
+            Ok((start + offset, usize::MAX))
+        }
+    }
+
+    /// Ponteiro de pedido com tamanho zero: alinhado, não nulo e fora da
+    /// memória, sem ocupar bloco nenhum (o `dealloc` de tamanho zero não faz nada).
+    fn dangling(layout: Layout) -> *mut u8 {
+        core::ptr::without_provenance_mut(layout.align())
+    }
+
+    /// Offset do ponteiro, já conferindo (no modo checado) o alinhamento do layout.
+    fn checked_offset(&self, ptr: *mut u8, layout: Layout) -> Result<usize, Diagnostic> {
+        let offset = self
+            .identify_adress(ptr)
+            .ok_or(Diagnostic::OutsideArena { addr: ptr as usize })?;
+        if self.checked && !(ptr as usize).is_multiple_of(layout.align()) {
+            return Err(Diagnostic::Misaligned { offset, align: layout.align() });
+        }
+        Ok(offset)
+    }
+
+    /// Modo checado: confere o bloco em `offset` sem soltar nada (célula ou
+    /// bloco do backend).
+    fn check(&self, offset: usize, layout: Layout) -> Result<(), Diagnostic> {
+        let i = self.shard_of(offset);
+        if self.cell_class(offset).is_some() {
+            // Safety: os runs foram todos registrados nessa mesma memória
+            return unsafe { self.arenas[i].small.lock().check(self.memory.base(), offset, layout) };
+        }
+        let (start, region) = self.shard_region(i);
+        let heap = self.arenas[i].heap.lock();
+        heap.backend.check(region, offset - start, layout).map_err(|d| d.shifted(start))
+    }
+
+    /// Classe do cache por thread que atende o layout, se o cache estiver ligado
+    /// (e o modo checado desligado).
+    fn cached_class(&self, layout: Layout) -> Option<usize> {
+        if !self.thread_cache || self.checked {
+            return None;
+        }
+        SmallHeap::class_of(layout)
+    }
+
+    /// Layout com que os blocos da classe são reservados quando o cache tá
+    /// ligado (o tamanho da classe inteira, alinhado nele).
+    fn class_layout(class: usize) -> Layout {
+        let size = small::CLASSES[class];
+        // Safety: as classes são potências de 2
+        unsafe { Layout::from_size_align_unchecked(size, size) }
+    }
+
+    /// Classe pequena que atende o layout, se a camada estiver ligada.
+    fn small_class(&self, layout: Layout) -> Option<usize> {
+        if !self.size_classes {
+            return None;
+        }
+        SmallHeap::class_of(layout)
+    }
+
+    /// Classe da célula em `offset` (None se não for célula de run nenhum).
+    fn cell_class(&self, offset: usize) -> Option<usize> {
+        if !self.size_classes {
+            return None;
+        }
+        self.arenas[self.shard_of(offset)].small.lock().class_at(offset)
+    }
+
+    /// Pega uma célula da classe na arena `i`, puxando um run novo da memória
+    /// se precisar.
+    fn alloc_cell(&self, i: usize, class: usize, stamp: &mut Stamp) -> Option<usize> {
+        let mut heap = self.arenas[i].small.lock();
+        let base = self.memory.base();
+        // Safety: os runs foram todos registrados nessa mesma memória
+        let mut offset = unsafe { heap.pop(base, class) };
+        if offset.is_none() && heap.has_room_for_run() {
+            // O run sozinho não é o evento, a célula que sai dele é
+            let (run, _) = self.alloc_gap(i, SmallHeap::run_layout(class), None, &mut Stamp::Skip).ok()?;
+            unsafe {
+                heap.add_run(base, class, run);
+                offset = heap.pop(base, class);
+            }
+        }
+        if offset.is_some() {
+            self.stamp(stamp);
+        }
+        offset
+    }
+}
+
+impl<const MEM: usize, const HIST: usize, L: RawLock, B: Backend, const ARENAS: usize> Default
+    for AlphaAlocator<MEM, HIST, L, B, ARENAS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const MEM: usize, const HIST: usize, L: RawLock + Sync, B: Backend + Send, const ARENAS: usize>
+    GlobalAlloc for AlphaAlocator<MEM, HIST, L, B, ARENAS>
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Counters::bump(&self.counters.allocs);
+        let mut stamp = Stamp::Pending;
+        let result = self.alloc_unlogged(layout, false, &mut stamp);
+        self.finish(EventKind::Alloc, layout, result, stamp)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Counters::bump(&self.counters.allocs);
+        let mut stamp = Stamp::Pending;
+        let result = self.alloc_unlogged(layout, true, &mut stamp);
+        self.finish(EventKind::AllocZeroed, layout, result, stamp)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Counters::bump(&self.counters.deallocs);
+        let mut stamp = Stamp::Pending;
+        let outcome = match self.dealloc_unlogged(ptr, layout, &mut stamp) {
+            Ok(()) => Outcome::Ok,
+            Err(diagnostic) => Outcome::Rejected(diagnostic),
+        };
+        self.record(EventKind::Dealloc, ptr, layout, outcome, stamp);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Counters::bump(&self.counters.reallocs);
+        let mut stamp = Stamp::Pending;
+        let result = self.realloc_unlogged(ptr, layout, new_size, &mut stamp);
+        let kind = EventKind::Realloc {
+            old_offset: self.identify_adress(ptr),
+            old_size: layout.size(),
+        };
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        self.finish(kind, new_layout, result, stamp)
+    }
+}
+
+impl<const MEM: usize, const HIST: usize, L: RawLock + Sync, B: Backend + Send, const ARENAS: usize>
+    AlphaAlocator<MEM, HIST, L, B, ARENAS>
+{
+    /// Aloca direto na arena `arena`, sem passar pelo cache por thread e sem
+    /// tentar as outras arenas se essa não der conta. Null se a arena não
+    /// existe.
+    ///
+    /// # Safety
+    ///
+    /// As mesmas do [`GlobalAlloc::alloc`]. O bloco é solto normalmente, com
+    /// `dealloc` (que acha a arena pelo endereço).
+    pub unsafe fn alloc_in(&self, arena: usize, layout: Layout) -> *mut u8 {
+        if arena >= ARENAS {
+            return core::ptr::null_mut();
+        }
+        Counters::bump(&self.counters.allocs);
+        let mut stamp = Stamp::Pending;
+        let result = if layout.size() == 0 {
+            Ok(Self::dangling(layout))
+        } else {
+            // Com o cache ligado o `dealloc` trata bloco pequeno como bloco da
+            // classe inteira, então ele tem que nascer desse tamanho
+            let class_layout = self.cached_class(layout).map_or(layout, Self::class_layout);
+            self.alloc_from(class_layout, Some(arena), false, &mut stamp)
+        };
+        self.finish(EventKind::Alloc, layout, result, stamp)
+    }
+
+    /// Registra o evento de um pedido que devolve ponteiro e devolve o ponteiro
+    /// (null se não deu).
+    fn finish(&self, kind: EventKind, layout: Layout, result: Result<*mut u8, Outcome>, stamp: Stamp) -> *mut u8 {
+        let (ptr, outcome) = match result {
+            Ok(ptr) => (ptr, Outcome::Ok),
+            Err(outcome) => (core::ptr::null_mut(), outcome),
+        };
+        self.record(kind, ptr, layout, outcome, stamp);
+        ptr
+    }
+
+    /// O `alloc`/`alloc_zeroed` sem registrar evento: tamanho zero, cache por
+    /// thread e depois as arenas.
+    unsafe fn alloc_unlogged(&self, layout: Layout, zeroed: bool, stamp: &mut Stamp) -> Result<*mut u8, Outcome> {
+        if layout.size() == 0 {
+            return Ok(Self::dangling(layout));
+        }
+        let mut layout = layout;
+        if let Some(class) = self.cached_class(layout) {
+            if let Some(ptr) = self.cache_pop(class) {
+                if zeroed {
+                    // Bloco reaproveitado, com certeza sujo
+                    core::ptr::write_bytes(ptr, 0, layout.size());
+                }
+                return Ok(ptr);
+            }
+            layout = Self::class_layout(class);
+        }
+        self.alloc_from(layout, None, zeroed, stamp)
+    }
+
+    /// Aloca nas arenas (ou só na `only`) e zera o que precisar.
+    unsafe fn alloc_from(
+        &self,
+        layout: Layout,
+        only: Option<usize>,
+        zeroed: bool,
+        stamp: &mut Stamp,
+    ) -> Result<*mut u8, Outcome> {
+        let (offset, dirty_end) = self.alloc_block(layout, only, stamp).map_err(Outcome::Failed)?;
+        let ptr = self.memory.base().add(offset);
+        // Só precisa zerar o pedaço que fica abaixo do high water mark,
+        // o resto a memória nunca entregou (ainda tá zerado desde o início)
+        if zeroed && offset < dirty_end {
+            let dirty = (dirty_end - offset).min(layout.size());
+            core::ptr::write_bytes(ptr, 0, dirty);
+        }
+        Ok(ptr)
+    }
+
+    /// O `dealloc` sem registrar evento. O diagnóstico (se tiver) já foi pro sink.
+    unsafe fn dealloc_unlogged(&self, ptr: *mut u8, layout: Layout, stamp: &mut Stamp) -> Result<(), Diagnostic> {
+        // Tamanho zero veio do `dangling`, não tem nada pra soltar
+        if layout.size() == 0 {
+            return Ok(());
+        }
+        match self.cached_class(layout) {
+            Some(class) => {
+                if self.cache_push(class, ptr) {
+                    return Ok(());
+                }
+                self.dealloc_shared(ptr, Self::class_layout(class), stamp)
+            }
+            None => self.dealloc_shared(ptr, layout, stamp),
+        }
+    }
+
+    /// O `realloc` sem registrar evento.
+    unsafe fn realloc_unlogged(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        stamp: &mut Stamp,
+    ) -> Result<*mut u8, Outcome> {
+        // De ou pra tamanho zero não tem o que aproveitar no lugar
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        if layout.size() == 0 {
+            return self.alloc_unlogged(new_layout, false, stamp);
+        }
+        if new_size == 0 {
+            self.dealloc_unlogged(ptr, layout, stamp).map_err(Outcome::Rejected)?;
+            return Ok(Self::dangling(new_layout));
+        }
+        // Bloco do cache (ou que vai virar um) tem o tamanho da classe: ou
+        // continua na mesma classe ou muda de lugar
+        let (old_class, new_class) = (self.cached_class(layout), self.cached_class(new_layout));
+        if old_class.is_some() || new_class.is_some() {
+            if old_class == new_class {
+                return Ok(ptr);
+            }
+            return self.realloc_moving(ptr, layout, new_size, stamp);
+        }
+        if self.checked {
+            // Layout errado no realloc estraga do mesmo jeito que no dealloc:
+            // avisa e devolve null, o bloco antigo fica como tá
+            if let Err(diagnostic) = self.checked_offset(ptr, layout).and_then(|offset| self.check(offset, layout)) {
+                self.report(diagnostic);
+                return Err(Outcome::Rejected(diagnostic));
+            }
+        }
+        // Primeiro tenta resolver no lugar, sem mover o bloco
+        if let Some(offset) = self.identify_adress(ptr) {
+            if let Some(class) = self.cell_class(offset) {
+                // Célula: continua no lugar se o tamanho novo ainda cabe na classe
+                if self.small_class(new_layout) == Some(class) {
+                    return Ok(ptr);
+                }
+                return self.realloc_moving(ptr, layout, new_size, stamp);
+            }
+            // Encolher ou crescer em cima do buraco logo depois: o backend diz se dá
+            let i = self.shard_of(offset);
+            let (start, _) = self.shard_region(i);
+            let resized = self.with_heap(i, |heap, region| {
+                let local = offset - start;
+                let (old_reserved, new_reserved) = heap.backend.resize(region, local, layout, new_size)?;
+                heap.used = heap.used + new_reserved - old_reserved;
+                heap.high_water = heap.high_water.max(local + new_size);
+                self.stamp(stamp);
+                Some(())
+            });
+            if resized.is_some() {
+                return Ok(ptr);
+            }
+        }
+
+        self.realloc_moving(ptr, layout, new_size, stamp)
+    }
+
+    /// Realloc que não deu no lugar: aloca outro, copia e solta o antigo.
+    unsafe fn realloc_moving(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+        stamp: &mut Stamp,
+    ) -> Result<*mut u8, Outcome> {
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        // O `seq` sai quando o antigo é solto: é aí que outra thread pode
+        // pegar o lugar dele (o novo só é nosso até o realloc voltar)
+        let new_ptr = self.alloc_unlogged(new_layout, false, &mut Stamp::Skip)?;
+        core::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+        // Se o antigo não soltar o diagnóstico já foi pro sink, e o bloco novo
+        // já é de quem pediu
+        let _ = self.dealloc_unlogged(ptr, layout, stamp);
+        Ok(new_ptr)
+    }
+
+    /// Bloco da classe guardado no cache dessa thread, se tiver.
+    fn cache_pop(&self, class: usize) -> Option<*mut u8> {
+        #[cfg(feature = "std")]
+        return cache::pop(self as *const Self as *const (), Self::release_cached, class);
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = class;
+            None
+        }
+    }
+
+    /// Guarda o bloco no cache dessa thread (false se não coube).
+    fn cache_push(&self, class: usize, ptr: *mut u8) -> bool {
+        #[cfg(feature = "std")]
+        return cache::push(self as *const Self as *const (), Self::release_cached, class, ptr);
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = (class, ptr);
+            false
+        }
+    }
+
+    /// O que o cache chama pra devolver um bloco pro alocador `owner`.
+    #[cfg(feature = "std")]
+    unsafe fn release_cached(owner: *const (), ptr: *mut u8, class: usize) {
+        let owner = &*(owner as *const Self);
+        // O `dealloc` desse bloco já foi pro log quando ele entrou no cache
+        let _ = owner.dealloc_shared(ptr, Self::class_layout(class), &mut Stamp::Skip);
+    }
+
+    /// O `dealloc` de verdade, sem passar pelo cache por thread. Se recusar, o
+    /// diagnóstico vai pro sink e volta também.
+    unsafe fn dealloc_shared(&self, ptr: *mut u8, layout: Layout, stamp: &mut Stamp) -> Result<(), Diagnostic> {
+        let result = self.free_block(ptr, layout, stamp);
+        if let Err(diagnostic) = result {
+            self.report(diagnostic);
+        }
+        result
+    }
+
+    /// Devolve pro backend da arena `i` um run que saiu da classe.
+    fn free_run(&self, i: usize, class: usize, run: usize) {
+        let (start, _) = self.shard_region(i);
+        self.with_heap(i, |heap, region| {
+            // O run foi alocado por esse backend com esse mesmo layout
+            if let Some(freed) = heap.backend.dealloc(region, run - start, SmallHeap::run_layout(class)) {
+                heap.used -= freed;
+            }
+        });
+    }
+
+    /// Solta o bloco (célula ou bloco do backend) sem avisar ninguém.
+    unsafe fn free_block(&self, ptr: *mut u8, layout: Layout, stamp: &mut Stamp) -> Result<(), Diagnostic> {
+        // Vamos identificar qual slot corresponde a esse ponteiro:
+        let offset = self.checked_offset(ptr, layout)?;
+        // A arena dona sai do endereço
+        let i = self.shard_of(offset);
+        // Célula de classe pequena volta pra free list, não mexe na tabela.
+        // Conferência e push com a mesma trava, senão dois frees da mesma
+        // célula podiam passar os dois
+        if self.size_classes {
+            let mut heap = self.arenas[i].small.lock();
+            if let Some(class) = heap.class_at(offset) {
+                if self.checked {
+                    heap.check(self.memory.base(), offset, layout)?;
+                }
+                if self.small_class(layout) == Some(class) {
+                    if let Some(run) = heap.push(self.memory.base(), class, offset) {
+                        // Run ficou todo livre: volta pro backend, ainda com a
+                        // trava das classes (mesma ordem do `alloc_cell`)
+                        self.free_run(i, class, run);
+                    }
+                    self.stamp(stamp);
+                    return Ok(());
+                }
+            }
+        }
+        // O backend acha o bloco que começa nesse offset e solta (no modo
+        // checado, confere antes sem soltar a trava no meio)
+        let (start, _) = self.shard_region(i);
+        self.with_heap(i, |heap, region| {
+            let offset = offset - start;
+            if self.checked {
+                heap.backend.check(region, offset, layout)?;
+            }
+            let freed = heap.backend.dealloc(region, offset, layout).ok_or(Diagnostic::UnknownSlot { offset })?;
+            // Devolver a memória pra arena
+            heap.used -= freed;
+            self.stamp(stamp);
+            Ok(())
+        })
+        .map_err(|diagnostic: Diagnostic| diagnostic.shifted(start))
+    }
+}
+
+/// Escreve direto no stdout, sem montar String (alocar aqui cairia no próprio
+/// alocador quando ele é o global).
+#[cfg(feature = "std")]
+struct Stdout;
+
+#[cfg(feature = "std")]
+impl fmt::Write for Stdout {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
+
+/// Registra um `AlphaAlocator` como `#[global_allocator]` do binário.
+///
+/// ```ignore
+/// alocator::global_alocator!(ALOCATOR);
+/// // ou escolhendo os tamanhos:
+/// alocator::global_alocator!(ALOCATOR: alocator::AlphaAlocator<8192, 0>);
+/// ```
+#[macro_export]
+macro_rules! global_alocator {
+    ($name:ident) => {
+        $crate::global_alocator!($name: $crate::AlphaAlocator);
+    };
+    ($name:ident : $ty:ty) => {
+        #[global_allocator]
+        static $name: $ty = <$ty>::new();
+    };
+}
@@ -0,0 +1,44 @@
+//! Pedaços do `AlphaAlocator::dump_map`: o mapa é montado de [`BATCH`]
+//! linhas em [`BATCH`] linhas, contando quantos bytes de cada caractere caem no
+//! pedaço que o pedido ocupa e quantos em buraco livre (o resto é metadado,
+//! cabeçalho ou sobra do bloco).
+
+/// Caracteres por linha do mapa.
+pub(crate) const LINE: usize = 64;
+/// Linhas lidas de uma vez (uma passada pelos blocos e buracos por leva).
+pub(crate) const BATCH: usize = 4;
+
+/// Caractere de bloco em uso.
+pub const MAP_USED: char = '#';
+/// Caractere de buraco livre.
+pub const MAP_FREE: char = '.';
+/// Caractere do que não é nem pedido nem buraco: metadados do backend, sobra
+/// no fim da arena que não dá pra usar, cabeçalho e sobra dentro do bloco
+/// (arredondamento, alinhamento).
+pub const MAP_OVERHEAD: char = ':';
+
+/// Soma em `counts` os bytes de `[start, end)` que caem em cada caractere da
+/// leva que começa em `first` (cada caractere = `cell` bytes).
+pub(crate) fn add_range(counts: &mut [usize], first: usize, cell: usize, start: usize, end: usize) {
+    let last = first.saturating_add(cell.saturating_mul(counts.len()));
+    let (mut pos, end) = (start.max(first), end.min(last));
+    while pos < end {
+        let c = (pos - first) / cell;
+        let cell_end = (first + (c + 1) * cell).min(end);
+        counts[c] += cell_end - pos;
+        pos = cell_end;
+    }
+}
+
+/// Caractere de um pedaço de `len` bytes: ganha quem ocupa mais (empate vai
+/// pra "em uso", depois pra "livre").
+pub(crate) fn cell_char(len: usize, used: usize, free: usize) -> char {
+    let overhead = len - used - free;
+    if used > 0 && used >= free && used >= overhead {
+        MAP_USED
+    } else if free >= overhead {
+        MAP_FREE
+    } else {
+        MAP_OVERHEAD
+    }
+}
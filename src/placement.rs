@@ -0,0 +1,58 @@
+//! Política de escolha do buraco onde um bloco novo vai morar.
+
+/// Como o `AlphaAlocator` escolhe entre os buracos que cabem o pedido.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PlacementPolicy {
+    /// Primeiro buraco (em ordem de offset) que couber. É o comportamento antigo.
+    #[default]
+    FirstFit,
+    /// Igual o first-fit, mas começa a procurar de onde a última alocação
+    /// terminou (cursor que vai rodando) e dá a volta no fim da memória.
+    NextFit,
+    /// O menor buraco que couber (sobra menos lasca).
+    BestFit,
+    /// O maior buraco (a sobra continua grande o suficiente pra ser útil).
+    WorstFit,
+}
+
+impl PlacementPolicy {
+    /// Escolhe o offset entre os `gaps` (em ordem de offset).
+    ///
+    /// `fit(início, fim)` devolve o offset alinhado se o pedido cabe naquele
+    /// buraco; `cursor` só é usado pelo next-fit.
+    pub(crate) fn choose<G, F>(self, gaps: G, cursor: usize, fit: F) -> Option<usize>
+    where
+        G: Iterator<Item = (usize, usize)>,
+        F: Fn(usize, usize) -> Option<usize>,
+    {
+        let mut candidates = gaps.filter_map(|(start, end)| fit(start, end).map(|offset| (offset, end - start)));
+        match self {
+            PlacementPolicy::FirstFit => candidates.next().map(|(offset, _)| offset),
+            PlacementPolicy::NextFit => {
+                // Primeiro candidato depois do cursor; se não tiver, volta pro começo
+                let mut first = None;
+                for (offset, _) in candidates {
+                    if offset >= cursor {
+                        return Some(offset);
+                    }
+                    first.get_or_insert(offset);
+                }
+                first
+            }
+            // `min_by_key`/`max_by_key` desempatam de jeitos diferentes,
+            // então faz na mão pra sempre ficar com o de menor offset no empate
+            PlacementPolicy::BestFit => candidates
+                .fold(None, |best: Option<(usize, usize)>, c| match best {
+                    Some(b) if b.1 <= c.1 => Some(b),
+                    _ => Some(c),
+                })
+                .map(|(offset, _)| offset),
+            PlacementPolicy::WorstFit => candidates
+                .fold(None, |best: Option<(usize, usize)>, c| match best {
+                    Some(b) if b.1 >= c.1 => Some(b),
+                    _ => Some(c),
+                })
+                .map(|(offset, _)| offset),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Tabela de blocos em uso, sempre ordenada por offset.
+//!
+//! A tabela não tem tamanho fixo: ela mora no topo da própria memória e cresce
+//! pra baixo conforme precisa (de [`GROW`] em [`GROW`] entradas), então o
+//! número de blocos vivos só é limitado pela memória. Os blocos ficam
+//! compactados e em ordem de `index`, então achar buraco é só andar uma vez
+//! pela tabela, sem copiar nem ordenar nada na pilha.
+//!
+//! ```text
+//! 0                                    table_start          table_end = len()
+//! | blocos e buracos ...               | livre | entradas ... |
+//!                                              ^ entrada len-1  ^ entrada 0
+//! ```
+//!
+//! Se um bloco vivo encosta na tabela e ela precisa crescer, ela muda pro
+//! topo de um buraco que caiba (e aí fica no meio dos blocos, como se fosse
+//! mais um). Quando não sobra nenhum bloco acima dela, ela volta pro topo.
+
+use core::alloc::Layout;
+
+use crate::backend::{Backend, Block, Region};
+use crate::{AllocFailure, Diagnostic, PlacementPolicy};
+
+/// Um bloco em uso dentro da memória do alocador.
+#[derive(Clone, Copy)]
+pub struct Slot {
+    // `index` = offset (onde começa)
+    // `size` = tamanho do bloco
+    pub size: usize,
+    pub index: usize,
+}
+
+/// Bytes de cada entrada da tabela (offset e tamanho em u32).
+pub const ENTRY: usize = 8;
+/// Quantas entradas a tabela ganha (ou devolve) de cada vez.
+pub const GROW: usize = 8;
+
+/// Backend padrão: tabela de blocos ordenada, buraco escolhido pela
+/// [`PlacementPolicy`] do alocador.
+///
+/// Entrada ocupada é só a que tá em `0..len`, e toda ela tem `size > 0`: não
+/// existe entrada "vazia" (tipo `size: 0, index: 0`) que dê pra confundir com
+/// o bloco do offset 0, e dois blocos nunca começam no mesmo offset. Pedido de
+/// tamanho zero nem chega aqui (o alocador devolve um ponteiro dangling).
+pub struct SlotTable {
+    // Quantas entradas estão ocupadas (as de `len..capacity` não valem nada)
+    len: usize,
+    capacity: usize,
+    // Onde a tabela termina (a entrada 0 fica logo abaixo); 0 = topo da memória
+    table_end: usize,
+    // Onde a última alocação terminou (só o next-fit usa)
+    cursor: usize,
+}
+
+impl SlotTable {
+    pub const fn new() -> Self {
+        SlotTable {
+            len: 0,
+            capacity: 0,
+            table_end: 0,
+            cursor: 0,
+        }
+    }
+
+    /// Onde a tabela termina.
+    fn table_end(&self, region: Region) -> usize {
+        if self.table_end == 0 {
+            region.len()
+        } else {
+            self.table_end
+        }
+    }
+
+    /// Onde a tabela começa.
+    fn table_start(&self, region: Region) -> usize {
+        self.table_end(region) - self.capacity * ENTRY
+    }
+
+    fn entry_ptr(&self, region: Region, i: usize) -> *mut u32 {
+        unsafe { region.base().add(self.table_end(region) - (i + 1) * ENTRY) as *mut u32 }
+    }
+
+    fn get(&self, region: Region, i: usize) -> Slot {
+        let p = self.entry_ptr(region, i);
+        unsafe {
+            Slot {
+                index: p.read_unaligned() as usize,
+                size: p.add(1).read_unaligned() as usize,
+            }
+        }
+    }
+
+    fn set(&self, region: Region, i: usize, slot: Slot) {
+        let p = self.entry_ptr(region, i);
+        unsafe {
+            p.write_unaligned(slot.index as u32);
+            p.add(1).write_unaligned(slot.size as u32);
+        }
+    }
+
+    /// Blocos em uso, em ordem de offset.
+    fn iter(&self, region: Region) -> impl Iterator<Item = Slot> + '_ {
+        (0..self.len).map(move |i| self.get(region, i))
+    }
+
+    /// Quantos blocos ficam abaixo da tabela (os primeiros da ordem).
+    fn below_table(&self, region: Region) -> usize {
+        let start = self.table_start(region);
+        self.partition_point(region, |s| s.index < start)
+    }
+
+    /// Blocos em uso e a própria tabela (como um bloco a mais), em ordem de
+    /// offset: é o que os buracos têm que desviar.
+    fn obstacles(&self, region: Region) -> impl Iterator<Item = Slot> + '_ {
+        let split = self.below_table(region);
+        let table = Slot { size: self.capacity * ENTRY, index: self.table_start(region) };
+        (0..split)
+            .map(move |i| self.get(region, i))
+            .chain(core::iter::once(table))
+            .chain((split..self.len).map(move |i| self.get(region, i)))
+    }
+
+    /// Primeira posição cujo bloco não satisfaz `before` (busca binária).
+    fn partition_point(&self, region: Region, before: impl Fn(Slot) -> bool) -> usize {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if before(self.get(region, mid)) {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    /// Posição do primeiro bloco que começa em `offset`.
+    fn position(&self, region: Region, offset: usize) -> Option<usize> {
+        let i = self.partition_point(region, |s| s.index < offset);
+        if i < self.len && self.get(region, i).index == offset {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    /// Onde começa o que vem depois do bloco da posição `i`: o bloco
+    /// seguinte, a tabela ou o fim da memória.
+    fn next_start(&self, region: Region, i: usize) -> usize {
+        let slot = self.get(region, i);
+        self.obstacles(region)
+            .map(|s| s.index)
+            .find(|&start| start >= slot.index + slot.size)
+            .unwrap_or(region.len())
+    }
+
+    /// Estica a tabela pra baixo, se o fim do bloco logo abaixo dela deixar.
+    /// Tenta [`GROW`] entradas e, se não couber, pelo menos uma. Se nem uma
+    /// couber (tem bloco encostado), muda a tabela pra um buraco maior.
+    fn grow(&mut self, region: Region) -> bool {
+        let below = self.below_table(region);
+        let last_end = match below.checked_sub(1) {
+            Some(last) => {
+                let last = self.get(region, last);
+                last.index + last.size
+            }
+            None => 0,
+        };
+        let start = self.table_start(region);
+        for want in [GROW, 1] {
+            if start >= last_end + want * ENTRY {
+                self.capacity += want;
+                return true;
+            }
+        }
+        self.relocate(region)
+    }
+
+    /// Muda a tabela (com [`GROW`] entradas a mais, ou pelo menos uma) pro
+    /// topo do buraco mais alto em que ela cabe, e zera onde ela tava.
+    fn relocate(&mut self, region: Region) -> bool {
+        for want in [GROW, 1] {
+            let bytes = (self.capacity + want) * ENTRY;
+            let Some((_, end)) = self.gaps(region).filter(|&(start, end)| end >= start + bytes).last() else {
+                continue;
+            };
+            self.move_to(region, end);
+            self.capacity += want;
+            return true;
+        }
+        false
+    }
+
+    /// Copia as entradas pra uma tabela que termina em `end` (com a mesma
+    /// capacidade) e zera o que a tabela antiga ocupava e a nova não.
+    fn move_to(&mut self, region: Region, end: usize) {
+        let (old_start, old_end) = (self.table_start(region), self.table_end(region));
+        let used = self.len * ENTRY;
+        unsafe {
+            let base = region.base();
+            core::ptr::copy(base.add(old_end - used), base.add(end - used), used);
+            let new_start = end - self.capacity * ENTRY;
+            // As duas faixas podem se encostar (a tabela só subiu um pouco)
+            if end <= old_start || new_start >= old_end {
+                core::ptr::write_bytes(base.add(old_start), 0, old_end - old_start);
+            } else if end > old_end {
+                core::ptr::write_bytes(base.add(old_start), 0, new_start - old_start);
+            } else {
+                core::ptr::write_bytes(base.add(end), 0, old_end - end);
+            }
+        }
+        self.table_end = if end == region.len() { 0 } else { end };
+    }
+
+    /// Devolve pra memória o pedaço da tabela que sobrou, zerado (assim o que
+    /// tá acima do high water mark continua zerado), e leva a tabela de volta
+    /// pro topo quando não sobrou bloco acima dela.
+    fn shrink(&mut self, region: Region) {
+        while self.capacity - self.len >= 2 * GROW {
+            let start = self.table_start(region);
+            unsafe { core::ptr::write_bytes(region.base().add(start), 0, GROW * ENTRY) };
+            self.capacity -= GROW;
+        }
+        if self.table_end != 0 && self.below_table(region) == self.len {
+            self.move_to(region, region.len());
+        }
+    }
+
+    /// Insere mantendo a ordem. Retorna false se não coube mais entrada.
+    fn insert(&mut self, region: Region, slot: Slot) -> bool {
+        if self.len == self.capacity && !self.grow(region) {
+            return false;
+        }
+        let i = self.partition_point(region, |s| s.index <= slot.index);
+        // As entradas i..len descem uma casa (a tabela cresce pra baixo)
+        if i < self.len {
+            unsafe {
+                let from = self.entry_ptr(region, self.len - 1) as *const u8;
+                let dst = self.entry_ptr(region, self.len) as *mut u8;
+                core::ptr::copy(from, dst, (self.len - i) * ENTRY);
+            }
+        }
+        self.set(region, i, slot);
+        self.len += 1;
+        true
+    }
+
+    /// Tira o bloco da posição `i`, puxando os de depois uma casa pra trás.
+    fn remove(&mut self, region: Region, i: usize) -> Slot {
+        let slot = self.get(region, i);
+        if i + 1 < self.len {
+            unsafe {
+                let from = self.entry_ptr(region, self.len - 1) as *const u8;
+                let dst = self.entry_ptr(region, self.len - 2) as *mut u8;
+                core::ptr::copy(from, dst, (self.len - i - 1) * ENTRY);
+            }
+        }
+        self.len -= 1;
+        self.shrink(region);
+        slot
+    }
+
+    /// Buracos entre os blocos (e a tabela), em ordem: antes do primeiro,
+    /// entre consecutivos e depois do último (até o fim da memória). Cada item
+    /// é `(início, fim)`.
+    fn gaps(&self, region: Region) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let starts = core::iter::once(0).chain(self.obstacles(region).map(|s| s.index + s.size));
+        let ends = self.obstacles(region).map(|s| s.index).chain(core::iter::once(region.len()));
+        starts.zip(ends)
+    }
+}
+
+impl Default for SlotTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl Backend for SlotTable {
+    const INIT: Self = SlotTable::new();
+    // A tabela devolve o que encolhe zerado, o resto da memória livre ninguém toca
+    const KEEPS_FREE_MEMORY_CLEAN: bool = true;
+
+    fn alloc(&mut self, region: Region, layout: Layout, policy: PlacementPolicy) -> Result<Block, AllocFailure> {
+        let size = layout.size();
+        debug_assert!(size > 0, "tamanho zero não ocupa slot");
+        // Garante a entrada antes de procurar buraco, senão o bloco novo
+        // podia cair justo onde a tabela precisa crescer
+        if self.len == self.capacity && !self.grow(region) {
+            // Não sobrou nem uma entrada no topo da memória
+            return Err(AllocFailure::SlotTableFull);
+        }
+
+        // A tabela já tá ordenada por offset, então é só testar os buracos em
+        // ordem (antes do primeiro, entre consecutivos, depois do último) e
+        // deixar a política escolher entre os que couberem (já com o padding
+        // do alinhamento)
+        let offset = policy
+            .choose(self.gaps(region), self.cursor, |start, end| {
+                region.fit(start, end, size, layout.align())
+            })
+            // Se não achou buraco, bora mandar user pastar
+            .ok_or(AllocFailure::Fragmentation)?;
+
+        let inserted = self.insert(region, Slot { size, index: offset });
+        debug_assert!(inserted);
+        self.cursor = offset + size;
+        Ok(Block { offset, reserved: size })
+    }
+
+    fn dealloc(&mut self, region: Region, offset: usize, _layout: Layout) -> Option<usize> {
+        let i = self.position(region, offset)?;
+        Some(self.remove(region, i).size)
+    }
+
+    fn check(&self, region: Region, offset: usize, layout: Layout) -> Result<(), Diagnostic> {
+        // Último bloco que começa em `offset` ou antes: se não cobrir o offset,
+        // ninguém cobre (a tabela é ordenada e os blocos não se sobrepõem)
+        let i = self.partition_point(region, |s| s.index <= offset);
+        let slot = match i.checked_sub(1).map(|i| self.get(region, i)) {
+            Some(slot) if offset < slot.index + slot.size => slot,
+            _ => return Err(Diagnostic::DoubleFree { offset }),
+        };
+        if slot.index != offset {
+            Err(Diagnostic::InteriorPointer { offset, block: slot.index })
+        } else if slot.size != layout.size() {
+            Err(Diagnostic::SizeMismatch { offset, expected: slot.size, got: layout.size() })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn resize(&mut self, region: Region, offset: usize, _layout: Layout, new_size: usize) -> Option<(usize, usize)> {
+        let i = self.position(region, offset)?;
+        // Onde começa o próximo bloco depois do nosso (ou a tabela)
+        let next_start = self.next_start(region, i);
+        let mut slot = self.get(region, i);
+        let old_size = slot.size;
+        // Encolher sempre dá (só corta o final); crescer só se o buraco
+        // logo depois do bloco der conta
+        if new_size <= old_size || next_start - offset >= new_size {
+            slot.size = new_size;
+            self.set(region, i, slot);
+            Some((old_size, new_size))
+        } else {
+            None
+        }
+    }
+
+    fn metadata_bytes(&self, _region: Region) -> usize {
+        self.capacity * ENTRY
+    }
+
+    fn for_each_block(&self, region: Region, f: &mut dyn FnMut(Slot)) {
+        for slot in self.iter(region) {
+            f(slot);
+        }
+    }
+
+    fn for_each_gap(&self, region: Region, f: &mut dyn FnMut(usize, usize)) {
+        for (start, end) in self.gaps(region) {
+            if start < end {
+                f(start, end);
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Camada de objetos pequenos: classes de tamanho fixo com free list O(1).
+//!
+//! Cada classe pega um "run" (bloco de [`RUN_SIZE`] bytes alocado pela
+//! varredura normal, então aparece como um `Slot` qualquer) e corta ele em
+//! células do mesmo tamanho. Célula livre guarda no próprio corpo o offset da
+//! próxima célula livre, então a lista não ocupa nada fora da memória.
+//!
+//! Cada run conta quantas células dele estão livres. Quando todas voltam, o
+//! run sai da classe e volta pra memória geral, a não ser que seja o único
+//! run da classe (esse fica, senão alocar e soltar uma célula só ia pegar e
+//! devolver um run inteiro toda vez).
+
+use core::alloc::Layout;
+
+use crate::Diagnostic;
+
+/// Tamanhos das classes (cada célula também fica alinhada no próprio tamanho).
+pub(crate) const CLASSES: [usize; 5] = [8, 16, 32, 64, 128];
+/// Tamanho do bloco que uma classe pega da memória de cada vez.
+pub(crate) const RUN_SIZE: usize = 512;
+/// Quantos runs dá pra ter ao mesmo tempo (limita a memória presa em células).
+pub(crate) const MAX_RUNS: usize = 32;
+
+// Fim da free list
+const NIL: u32 = u32::MAX;
+
+#[derive(Clone, Copy)]
+struct Run {
+    offset: usize,
+    class: usize,
+    // Células desse run que estão na free list
+    free: usize,
+}
+
+pub(crate) struct SmallHeap {
+    heads: [u32; CLASSES.len()],
+    runs: [Run; MAX_RUNS],
+    run_count: usize,
+}
+
+impl SmallHeap {
+    pub const fn new() -> Self {
+        SmallHeap {
+            heads: [NIL; CLASSES.len()],
+            runs: [Run { offset: 0, class: 0, free: 0 }; MAX_RUNS],
+            run_count: 0,
+        }
+    }
+
+    /// Classe que atende esse layout (None se for grande demais ou vazio).
+    pub fn class_of(layout: Layout) -> Option<usize> {
+        if layout.size() == 0 {
+            return None;
+        }
+        let need = layout.size().max(layout.align());
+        CLASSES.iter().position(|&c| c >= need)
+    }
+
+    pub fn has_room_for_run(&self) -> bool {
+        self.run_count < MAX_RUNS
+    }
+
+    /// Layout com que o run de uma classe é pedido pra memória (e devolvido).
+    pub fn run_layout(class: usize) -> Layout {
+        // Safety: RUN_SIZE não é zero e as classes são potências de 2
+        unsafe { Layout::from_size_align_unchecked(RUN_SIZE, CLASSES[class]) }
+    }
+
+    /// Posição (em `runs`) do run que contém `offset`.
+    fn run_at(&self, offset: usize) -> Option<usize> {
+        self.runs[..self.run_count]
+            .iter()
+            .position(|r| offset >= r.offset && offset < r.offset + RUN_SIZE)
+    }
+
+    /// Classe do run que contém `offset`, se algum contiver.
+    pub fn class_at(&self, offset: usize) -> Option<usize> {
+        self.run_at(offset).map(|i| self.runs[i].class)
+    }
+
+    /// Modo checado: confere se `offset` (que tá dentro de algum run) é o
+    /// começo de uma célula em uso da classe desse layout. Anda pela free list
+    /// inteira da classe pra achar double free.
+    ///
+    /// # Safety
+    ///
+    /// Igual o `pop`.
+    pub unsafe fn check(&self, base: *mut u8, offset: usize, layout: Layout) -> Result<(), Diagnostic> {
+        let Some(run) = self.run_at(offset).map(|i| self.runs[i]) else {
+            return Err(Diagnostic::DoubleFree { offset });
+        };
+        let cell = CLASSES[run.class];
+        let start = run.offset + (offset - run.offset) / cell * cell;
+        if start != offset {
+            return Err(Diagnostic::InteriorPointer { offset, block: start });
+        }
+        if Self::class_of(layout) != Some(run.class) {
+            return Err(Diagnostic::SizeMismatch { offset, expected: cell, got: layout.size() });
+        }
+        let mut next = self.heads[run.class];
+        while next != NIL {
+            if next as usize == offset {
+                return Err(Diagnostic::DoubleFree { offset });
+            }
+            next = (base.add(next as usize) as *const u32).read();
+        }
+        Ok(())
+    }
+
+    /// Tira uma célula da free list da classe.
+    ///
+    /// # Safety
+    ///
+    /// `base` tem que ser o início da memória onde os runs foram registrados.
+    pub unsafe fn pop(&mut self, base: *mut u8, class: usize) -> Option<usize> {
+        let head = self.heads[class];
+        if head == NIL {
+            return None;
+        }
+        let offset = head as usize;
+        self.heads[class] = (base.add(offset) as *const u32).read();
+        if let Some(i) = self.run_at(offset) {
+            self.runs[i].free -= 1;
+        }
+        Some(offset)
+    }
+
+    /// Devolve a célula em `offset` pra free list da classe.
+    ///
+    /// Se com ela o run ficou todo livre (e a classe tem outro run), o run sai
+    /// da classe e o offset dele volta, pra quem chamou soltar o run na memória
+    /// com [`run_layout`](Self::run_layout).
+    ///
+    /// # Safety
+    ///
+    /// Igual o `pop`, e a célula tem que ser dessa classe e não estar mais em uso.
+    pub unsafe fn push(&mut self, base: *mut u8, class: usize, offset: usize) -> Option<usize> {
+        self.link(base, class, offset);
+        let i = self.run_at(offset)?;
+        self.runs[i].free += 1;
+        let siblings = self.runs[..self.run_count].iter().filter(|r| r.class == class).count();
+        if self.runs[i].free < RUN_SIZE / CLASSES[class] || siblings == 1 {
+            return None;
+        }
+        let run = self.runs[i].offset;
+        self.unlink_run(base, class, run);
+        self.run_count -= 1;
+        self.runs[i] = self.runs[self.run_count];
+        Some(run)
+    }
+
+    /// Põe a célula na frente da free list, sem mexer na conta do run.
+    unsafe fn link(&mut self, base: *mut u8, class: usize, offset: usize) {
+        (base.add(offset) as *mut u32).write(self.heads[class]);
+        self.heads[class] = offset as u32;
+    }
+
+    /// Tira da free list da classe todas as células do run em `run`.
+    unsafe fn unlink_run(&mut self, base: *mut u8, class: usize, run: usize) {
+        let mut prev: Option<usize> = None;
+        let mut cur = self.heads[class];
+        while cur != NIL {
+            let next = (base.add(cur as usize) as *const u32).read();
+            if (run..run + RUN_SIZE).contains(&(cur as usize)) {
+                match prev {
+                    None => self.heads[class] = next,
+                    Some(p) => (base.add(p) as *mut u32).write(next),
+                }
+            } else {
+                prev = Some(cur as usize);
+            }
+            cur = next;
+        }
+    }
+
+    /// Corta o run em `offset` em células da classe e joga todas na free list.
+    ///
+    /// # Safety
+    ///
+    /// Igual o `pop`, e `[offset, offset + RUN_SIZE)` tem que ser nosso (já
+    /// registrado) e alinhado no tamanho da classe.
+    pub unsafe fn add_run(&mut self, base: *mut u8, class: usize, offset: usize) {
+        let cell = CLASSES[class];
+        self.runs[self.run_count] = Run {
+            offset,
+            class,
+            free: RUN_SIZE / cell,
+        };
+        self.run_count += 1;
+        // Empilha de trás pra frente pra primeira célula sair primeiro
+        for i in (0..RUN_SIZE / cell).rev() {
+            self.link(base, class, offset + i * cell);
+        }
+    }
+}
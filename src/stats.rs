@@ -0,0 +1,172 @@
+//! Números do alocador pra quem quer acompanhar o uso da memória sem ter que
+//! andar pelos blocos na mão (ver `AlphaAlocator::stats`).
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::AllocFailure;
+
+/// Foto do alocador num momento.
+///
+/// Bytes, blocos e buracos são lidos com a trava de todas as arenas pega, então
+/// batem entre si. Os contadores de chamadas são lidos no mesmo momento, mas
+/// contam antes do pedido pegar a trava, então podem estar um pedido na frente.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Bytes reservados pros blocos vivos (o que o backend reservou, não o
+    /// que foi pedido). Runs das classes pequenas e blocos guardados no cache
+    /// por thread contam como vivos.
+    pub live_bytes: usize,
+    /// Bytes que os backends usam pra se organizar (tabelas, bitmaps, sobra).
+    pub metadata_bytes: usize,
+    /// Maior ocupação (blocos mais metadados) que a memória já teve.
+    pub peak_bytes: usize,
+    /// Chamadas de `alloc`/`alloc_zeroed`/`alloc_in` (inclusive as que falharam).
+    pub allocs: usize,
+    /// Chamadas de `dealloc` (inclusive as recusadas).
+    pub deallocs: usize,
+    /// Chamadas de `realloc`.
+    pub reallocs: usize,
+    /// Quantas vezes faltou memória, por motivo.
+    pub failures: FailureCounts,
+    /// Blocos vivos do backend (cada run das classes pequenas conta como um).
+    pub live_blocks: usize,
+    /// Maior buraco livre contíguo, somando todas as arenas (um pedido tem que
+    /// caber inteiro numa arena só).
+    pub largest_free_gap: usize,
+}
+
+/// Falhas de alocação separadas por [`AllocFailure`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FailureCounts {
+    pub out_of_memory: usize,
+    pub fragmentation: usize,
+    pub slot_table_full: usize,
+}
+
+impl FailureCounts {
+    /// Quantas vezes faltou memória por esse motivo.
+    pub fn get(&self, reason: AllocFailure) -> usize {
+        match reason {
+            AllocFailure::OutOfMemory => self.out_of_memory,
+            AllocFailure::Fragmentation => self.fragmentation,
+            AllocFailure::SlotTableFull => self.slot_table_full,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.out_of_memory + self.fragmentation + self.slot_table_full
+    }
+}
+
+/// Contadores de chamadas (atômicos, fora das travas).
+pub(crate) struct Counters {
+    pub allocs: AtomicUsize,
+    pub deallocs: AtomicUsize,
+    pub reallocs: AtomicUsize,
+    failures: [AtomicUsize; 3],
+}
+
+impl Counters {
+    pub const fn new() -> Self {
+        Counters {
+            allocs: AtomicUsize::new(0),
+            deallocs: AtomicUsize::new(0),
+            reallocs: AtomicUsize::new(0),
+            failures: [const { AtomicUsize::new(0) }; 3],
+        }
+    }
+
+    pub fn bump(counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn fail(&self, reason: AllocFailure) {
+        let i = match reason {
+            AllocFailure::OutOfMemory => 0,
+            AllocFailure::Fragmentation => 1,
+            AllocFailure::SlotTableFull => 2,
+        };
+        Self::bump(&self.failures[i]);
+    }
+
+    /// Copia os contadores pra dentro da foto.
+    pub fn fill(&self, stats: &mut Stats) {
+        stats.allocs = self.allocs.load(Ordering::Relaxed);
+        stats.deallocs = self.deallocs.load(Ordering::Relaxed);
+        stats.reallocs = self.reallocs.load(Ordering::Relaxed);
+        let [oom, frag, full] = &self.failures;
+        stats.failures = FailureCounts {
+            out_of_memory: oom.load(Ordering::Relaxed),
+            fragmentation: frag.load(Ordering::Relaxed),
+            slot_table_full: full.load(Ordering::Relaxed),
+        };
+    }
+}
+
+/// Quantas faixas tem o histograma de buracos (uma por potência de 2).
+pub const GAP_BUCKETS: usize = usize::BITS as usize;
+
+/// Como a memória livre tá picotada (ver `AlphaAlocator::fragmentation`).
+///
+/// Só conta os buracos dos backends: células livres dos runs das classes
+/// pequenas e blocos no cache por thread não entram.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fragmentation {
+    /// Soma de todos os buracos.
+    pub free_bytes: usize,
+    /// Quantos buracos.
+    pub gaps: usize,
+    pub largest_gap: usize,
+    /// `histogram[i]` = quantos buracos têm entre `2^i` e `2^(i+1) - 1` bytes.
+    pub histogram: [usize; GAP_BUCKETS],
+}
+
+impl Fragmentation {
+    pub const fn new() -> Self {
+        Fragmentation {
+            free_bytes: 0,
+            gaps: 0,
+            largest_gap: 0,
+            histogram: [0; GAP_BUCKETS],
+        }
+    }
+
+    pub(crate) fn add(&mut self, size: usize) {
+        if size == 0 {
+            return;
+        }
+        self.free_bytes += size;
+        self.gaps += 1;
+        self.largest_gap = self.largest_gap.max(size);
+        self.histogram[size.ilog2() as usize] += 1;
+    }
+
+    /// Fragmentação externa: `1 - maior buraco / livre total`. 0 quando o livre
+    /// tá todo num buraco só (ou não tem livre nenhum), perto de 1 quando tá
+    /// espalhado em lascas.
+    pub fn ratio(&self) -> f32 {
+        if self.free_bytes == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_gap as f32 / self.free_bytes as f32
+        }
+    }
+}
+
+impl Default for Fragmentation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// O que foi guardado da última vez que faltou memória (ver
+/// `AlphaAlocator::last_failure_snapshot`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FailureSnapshot {
+    pub reason: AllocFailure,
+    /// Layout do pedido que falhou.
+    pub size: usize,
+    pub align: usize,
+    /// A memória das arenas tentadas, contada na hora da falha.
+    pub fragmentation: Fragmentation,
+}
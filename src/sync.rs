@@ -0,0 +1,139 @@
+//! Travas usadas pelo alocador.
+//!
+//! Nada aqui depende de sistema operacional nem aloca: o alocador escolhe a
+//! trava crua pelo parâmetro de tipo (ver [`RawLock`]) e o [`Lock`] só embrulha
+//! o dado protegido. Sem envenenamento (poison) como no `std::sync::Mutex`, então
+//! um panic em outra thread não faz o alocador inteiro começar a dar panic.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Uma trava crua (sem dado dentro), no estilo do `lock_api`.
+///
+/// # Safety
+///
+/// Quem implementa garante exclusão mútua: entre um `lock` que retornou e o
+/// `unlock` correspondente, nenhum outro `lock` pode retornar.
+pub unsafe trait RawLock {
+    /// Valor inicial (destravado), pra poder montar tudo em `const fn`.
+    const INIT: Self;
+
+    /// Bloqueia até conseguir a trava.
+    fn lock(&self);
+
+    /// Solta a trava.
+    ///
+    /// # Safety
+    ///
+    /// Só pode ser chamado por quem está segurando a trava.
+    unsafe fn unlock(&self);
+}
+
+/// Spin lock feito só com um `AtomicBool`. É a trava padrão do `AlphaAlocator`.
+pub struct SpinLock {
+    locked: AtomicBool,
+}
+
+unsafe impl RawLock for SpinLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = SpinLock {
+        locked: AtomicBool::new(false),
+    };
+
+    fn lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // Espera sem ficar martelando o compare_exchange
+            while self.locked.load(Ordering::Relaxed) {
+                core::hint::spin_loop();
+            }
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+/// Trava baseada no crate `critical-section`: segura uma seção crítica
+/// (normalmente interrupções desligadas) enquanto a trava estiver pega.
+///
+/// Boa pra microcontrolador de um núcleo só, onde um spin lock pego dentro de
+/// uma interrupção travaria pra sempre.
+#[cfg(feature = "critical-section")]
+pub struct CriticalSectionLock {
+    state: UnsafeCell<core::mem::MaybeUninit<critical_section::RestoreState>>,
+}
+
+// Safety: o `state` só é tocado dentro da seção crítica.
+#[cfg(feature = "critical-section")]
+unsafe impl Sync for CriticalSectionLock {}
+
+#[cfg(feature = "critical-section")]
+unsafe impl RawLock for CriticalSectionLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = CriticalSectionLock {
+        state: UnsafeCell::new(core::mem::MaybeUninit::uninit()),
+    };
+
+    fn lock(&self) {
+        let state = unsafe { critical_section::acquire() };
+        unsafe { (*self.state.get()).write(state) };
+    }
+
+    unsafe fn unlock(&self) {
+        let state = (*self.state.get()).assume_init();
+        critical_section::release(state);
+    }
+}
+
+/// Dado protegido por uma trava crua `R`.
+pub(crate) struct Lock<R: RawLock, T> {
+    raw: R,
+    value: UnsafeCell<T>,
+}
+
+// Safety: o `value` só é acessado por quem segura o `raw`.
+unsafe impl<R: RawLock + Sync, T: Send> Sync for Lock<R, T> {}
+
+pub(crate) struct LockGuard<'a, R: RawLock, T> {
+    lock: &'a Lock<R, T>,
+}
+
+impl<R: RawLock, T> Lock<R, T> {
+    pub const fn new(value: T) -> Self {
+        Lock {
+            raw: R::INIT,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn lock(&self) -> LockGuard<'_, R, T> {
+        self.raw.lock();
+        LockGuard { lock: self }
+    }
+}
+
+impl<R: RawLock, T> Deref for LockGuard<'_, R, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<R: RawLock, T> DerefMut for LockGuard<'_, R, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<R: RawLock, T> Drop for LockGuard<'_, R, T> {
+    fn drop(&mut self) {
+        unsafe { self.lock.raw.unlock() };
+    }
+}
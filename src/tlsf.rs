@@ -0,0 +1,453 @@
+//! Backend TLSF (two-level segregated fit): `alloc` e `dealloc` em tempo
+//! constante no pior caso, pra quem precisa pôr um limite de latência no papel.
+//!
+//! Cada bloco (livre ou em uso) começa com um cabeçalho de [`HEADER`] bytes
+//! dentro da própria memória: tamanho, flag de livre e o offset do bloco
+//! físico anterior. Os blocos cobrem a memória inteira sem buraco, então
+//! juntar com os vizinhos no `dealloc` é só olhar o anterior e o seguinte.
+//! Os blocos livres ficam em free lists separadas por faixa de tamanho (dois
+//! níveis: potência de 2 e 16 subdivisões lineares dentro dela), e dois
+//! bitmaps dizem quais listas têm alguém, então achar um bloco que cabe é
+//! meia dúzia de instruções de bit.
+
+use core::alloc::Layout;
+
+use crate::backend::{live_block_containing, read_slack, write_slack, Backend, Block, Region};
+use crate::{AllocFailure, Diagnostic, PlacementPolicy, Slot};
+
+/// Bytes de cabeçalho antes de cada ponteiro entregue.
+pub const HEADER: usize = 8;
+// Tamanho de bloco sempre múltiplo disso (e o ponteiro entregue alinhado nisso)
+const ALIGN: usize = 8;
+// Bloco livre precisa de cabeçalho + links da free list
+const MIN_BLOCK: usize = HEADER + 8;
+
+const SL_LOG: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG;
+// Abaixo de SMALL as listas são lineares (de 8 em 8 bytes)
+const FL_SHIFT: u32 = SL_LOG + ALIGN.trailing_zeros();
+const SMALL: usize = 1 << FL_SHIFT;
+// Tamanhos cabem em u32
+const FL_COUNT: usize = 32 - FL_SHIFT as usize + 1;
+
+const NIL: u32 = u32::MAX;
+// Bit 0 do tamanho (que é sempre múltiplo de 8) marca bloco livre
+const FREE_BIT: u32 = 1;
+// Bit 1 marca bloco em uso com sobra depois do pedido, anotada no fim dele
+const SLACK_BIT: u32 = 2;
+
+/// Cabeçalho de todo bloco.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Header {
+    size: u32,
+    prev_phys: u32,
+}
+
+/// Links da free list, logo depois do cabeçalho de um bloco livre.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Links {
+    next: u32,
+    prev: u32,
+}
+
+/// Backend TLSF (ver doc do módulo). Ignora a `PlacementPolicy`: o bloco é
+/// sempre o primeiro da menor lista que garante caber (good-fit).
+pub struct Tlsf {
+    fl_bitmap: u32,
+    sl_bitmap: [u32; FL_COUNT],
+    heads: [[u32; SL_COUNT]; FL_COUNT],
+    // Monta o bloco livre inicial só no primeiro uso (precisa da região)
+    ready: bool,
+    end: usize,
+    free_bytes: usize,
+}
+
+impl Tlsf {
+    pub const fn new() -> Self {
+        Tlsf {
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            heads: [[NIL; SL_COUNT]; FL_COUNT],
+            ready: false,
+            end: 0,
+            free_bytes: 0,
+        }
+    }
+
+    fn init(&mut self, region: Region) {
+        self.end = region.len().min(u32::MAX as usize) & !(ALIGN - 1);
+        self.ready = true;
+        if self.end >= MIN_BLOCK {
+            Self::set_header(region, 0, Header { size: self.end as u32 | FREE_BIT, prev_phys: NIL });
+            self.insert(region, 0, self.end);
+        }
+    }
+
+    /// Lista (fl, sl) onde um bloco livre desse tamanho mora.
+    fn mapping_insert(size: usize) -> (usize, usize) {
+        if size < SMALL {
+            (0, size / (SMALL / SL_COUNT))
+        } else {
+            let fl = usize::BITS - 1 - size.leading_zeros();
+            let sl = (size >> (fl - SL_LOG)) ^ SL_COUNT;
+            ((fl - FL_SHIFT + 1) as usize, sl)
+        }
+    }
+
+    /// Primeira lista em que qualquer bloco tem pelo menos `size` bytes
+    /// (arredonda pra cima até a próxima subdivisão).
+    fn mapping_search(size: usize) -> Option<(usize, usize)> {
+        let size = if size >= SMALL {
+            let round = (1 << (usize::BITS - 1 - size.leading_zeros() - SL_LOG)) - 1;
+            size.checked_add(round)?
+        } else {
+            size
+        };
+        let (fl, sl) = Self::mapping_insert(size);
+        if fl < FL_COUNT {
+            Some((fl, sl))
+        } else {
+            None
+        }
+    }
+
+    /// Lista não vazia a partir de (fl, sl), usando só os bitmaps.
+    fn find_suitable(&self, fl: usize, sl: usize) -> Option<(usize, usize)> {
+        let mut fl = fl;
+        let mut sl_map = self.sl_bitmap[fl] & (!0u32 << sl);
+        if sl_map == 0 {
+            let fl_map = self.fl_bitmap & (!0u32).checked_shl(fl as u32 + 1).unwrap_or(0);
+            if fl_map == 0 {
+                return None;
+            }
+            fl = fl_map.trailing_zeros() as usize;
+            sl_map = self.sl_bitmap[fl];
+        }
+        Some((fl, sl_map.trailing_zeros() as usize))
+    }
+
+    fn header(region: Region, block: usize) -> Header {
+        unsafe { (region.base().add(block) as *const Header).read_unaligned() }
+    }
+
+    fn set_header(region: Region, block: usize, header: Header) {
+        unsafe { (region.base().add(block) as *mut Header).write_unaligned(header) }
+    }
+
+    fn links(region: Region, block: usize) -> Links {
+        unsafe { (region.base().add(block + HEADER) as *const Links).read_unaligned() }
+    }
+
+    fn set_links(region: Region, block: usize, links: Links) {
+        unsafe { (region.base().add(block + HEADER) as *mut Links).write_unaligned(links) }
+    }
+
+    fn size_of(region: Region, block: usize) -> usize {
+        (Self::header(region, block).size & !(FREE_BIT | SLACK_BIT)) as usize
+    }
+
+    fn is_free(region: Region, block: usize) -> bool {
+        Self::header(region, block).size & FREE_BIT != 0
+    }
+
+    /// Reescreve o tamanho (e a flag de livre) mantendo o `prev_phys`, e
+    /// avisa o bloco seguinte de onde ele começa.
+    fn set_block(&self, region: Region, block: usize, size: usize, free: bool) {
+        let mut h = Self::header(region, block);
+        h.size = size as u32 | if free { FREE_BIT } else { 0 };
+        Self::set_header(region, block, h);
+        let next = block + size;
+        if next < self.end {
+            let mut n = Self::header(region, next);
+            n.prev_phys = block as u32;
+            Self::set_header(region, next, n);
+        }
+    }
+
+    /// Anota no bloco em uso quanto sobra no fim depois dos `requested` bytes
+    /// do pedido (só o mapa lê).
+    fn set_slack(region: Region, block: usize, size: usize, requested: usize) {
+        let slack = size - HEADER - requested;
+        let mut h = Self::header(region, block);
+        if slack > 0 {
+            h.size |= SLACK_BIT;
+            write_slack(region, block + size, slack);
+        } else {
+            h.size &= !SLACK_BIT;
+        }
+        Self::set_header(region, block, h);
+    }
+
+    /// Põe o bloco livre na lista do tamanho dele.
+    fn insert(&mut self, region: Region, block: usize, size: usize) {
+        let (fl, sl) = Self::mapping_insert(size);
+        let head = self.heads[fl][sl];
+        Self::set_links(region, block, Links { next: head, prev: NIL });
+        if head != NIL {
+            let mut l = Self::links(region, head as usize);
+            l.prev = block as u32;
+            Self::set_links(region, head as usize, l);
+        }
+        self.heads[fl][sl] = block as u32;
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+        self.free_bytes += size;
+    }
+
+    /// Tira o bloco livre da lista dele.
+    fn remove(&mut self, region: Region, block: usize, size: usize) {
+        let (fl, sl) = Self::mapping_insert(size);
+        let l = Self::links(region, block);
+        if l.prev == NIL {
+            self.heads[fl][sl] = l.next;
+        } else {
+            let mut p = Self::links(region, l.prev as usize);
+            p.next = l.next;
+            Self::set_links(region, l.prev as usize, p);
+        }
+        if l.next != NIL {
+            let mut n = Self::links(region, l.next as usize);
+            n.prev = l.prev;
+            Self::set_links(region, l.next as usize, n);
+        }
+        if self.heads[fl][sl] == NIL {
+            self.sl_bitmap[fl] &= !(1 << sl);
+            if self.sl_bitmap[fl] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
+        self.free_bytes -= size;
+    }
+
+    /// Separa o fim do bloco em uso (se sobrar pelo menos um bloco mínimo) e
+    /// devolve a sobra, juntando com o vizinho de depois se ele for livre.
+    fn trim(&mut self, region: Region, block: usize, size: usize, keep: usize) -> usize {
+        if size - keep < MIN_BLOCK {
+            return size;
+        }
+        self.set_block(region, block, keep, false);
+        let rest = block + keep;
+        let mut rest_size = size - keep;
+        let next = rest + rest_size;
+        Self::set_header(region, rest, Header { size: 0, prev_phys: block as u32 });
+        if next < self.end && Self::is_free(region, next) {
+            let next_size = Self::size_of(region, next);
+            self.remove(region, next, next_size);
+            rest_size += next_size;
+        }
+        self.set_block(region, rest, rest_size, true);
+        self.insert(region, rest, rest_size);
+        keep
+    }
+
+    /// Tamanho de bloco (com cabeçalho) pra guardar `size` bytes.
+    fn block_size(size: usize) -> Option<usize> {
+        let payload = size.max(1).checked_add(ALIGN - 1)? & !(ALIGN - 1);
+        Some((payload + HEADER).max(MIN_BLOCK))
+    }
+
+    /// Começo do bloco cujo ponteiro entregue é `offset`, se for um bloco em uso.
+    fn block_at(&self, region: Region, offset: usize) -> Option<usize> {
+        let block = offset.checked_sub(HEADER)?;
+        let valid = self.ready
+            && block + MIN_BLOCK <= self.end
+            && block.is_multiple_of(ALIGN)
+            && !Self::is_free(region, block)
+            && block + Self::size_of(region, block) <= self.end;
+        if valid {
+            Some(block)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Tlsf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl Backend for Tlsf {
+    const INIT: Self = Tlsf::new();
+    // Cabeçalhos e links moram na memória livre
+    const KEEPS_FREE_MEMORY_CLEAN: bool = false;
+
+    fn alloc(&mut self, region: Region, layout: Layout, _policy: PlacementPolicy) -> Result<Block, AllocFailure> {
+        if !self.ready {
+            self.init(region);
+        }
+        let need = Self::block_size(layout.size()).ok_or(AllocFailure::OutOfMemory)?;
+        // Alinhamento maior que o natural: pede folga pra poder cortar um
+        // bloco livre na frente (ele tem que ter pelo menos MIN_BLOCK)
+        let over_aligned = layout.align() > ALIGN;
+        let search = if over_aligned {
+            need.checked_add(layout.align() + MIN_BLOCK).ok_or(AllocFailure::OutOfMemory)?
+        } else {
+            need
+        };
+
+        let Some((fl, sl)) = Self::mapping_search(search).and_then(|(fl, sl)| self.find_suitable(fl, sl)) else {
+            return Err(if self.free_bytes < need {
+                AllocFailure::OutOfMemory
+            } else {
+                AllocFailure::Fragmentation
+            });
+        };
+        let mut block = self.heads[fl][sl] as usize;
+        let mut size = Self::size_of(region, block);
+        self.remove(region, block, size);
+
+        if over_aligned {
+            // Acha o primeiro ponteiro alinhado que deixa na frente ou nada
+            // ou um bloco livre de verdade
+            let mut user = region.align_up(block + HEADER, layout.align()).ok_or(AllocFailure::OutOfMemory)?;
+            if user - HEADER != block && user - HEADER - block < MIN_BLOCK {
+                user = region
+                    .align_up(block + HEADER + MIN_BLOCK, layout.align())
+                    .ok_or(AllocFailure::OutOfMemory)?;
+            }
+            let gap = user - HEADER - block;
+            if gap > 0 {
+                // O bloco de antes tá em uso (livres vizinhos sempre se juntam),
+                // então a sobra da frente vira um livre sozinho
+                self.set_block(region, block, gap, true);
+                self.insert(region, block, gap);
+                let moved = block + gap;
+                Self::set_header(region, moved, Header { size: 0, prev_phys: block as u32 });
+                block = moved;
+                size -= gap;
+            }
+        }
+
+        self.set_block(region, block, size, false);
+        let size = self.trim(region, block, size, need);
+        Self::set_slack(region, block, size, layout.size());
+        Ok(Block { offset: block + HEADER, reserved: size })
+    }
+
+    fn dealloc(&mut self, region: Region, offset: usize, _layout: Layout) -> Option<usize> {
+        let mut block = self.block_at(region, offset)?;
+        let freed = Self::size_of(region, block);
+        let mut size = freed;
+        // Marca o próprio cabeçalho como livre antes de juntar: se ele for
+        // engolido pelo anterior, um segundo free do mesmo ponteiro ainda cai no
+        // `block_at` como livre em vez de estragar as listas
+        let mut h = Self::header(region, block);
+        h.size |= FREE_BIT;
+        Self::set_header(region, block, h);
+
+        // Junta com o anterior e com o seguinte se estiverem livres
+        let prev = Self::header(region, block).prev_phys;
+        if prev != NIL && Self::is_free(region, prev as usize) {
+            let prev = prev as usize;
+            let prev_size = Self::size_of(region, prev);
+            self.remove(region, prev, prev_size);
+            block = prev;
+            size += prev_size;
+        }
+        let next = block + size;
+        if next < self.end && Self::is_free(region, next) {
+            let next_size = Self::size_of(region, next);
+            self.remove(region, next, next_size);
+            size += next_size;
+        }
+        self.set_block(region, block, size, true);
+        self.insert(region, block, size);
+        Some(freed)
+    }
+
+    fn check(&self, region: Region, offset: usize, layout: Layout) -> Result<(), Diagnostic> {
+        // Anda pelos blocos em vez de ler o cabeçalho em `offset - HEADER`:
+        // num ponteiro do meio do bloco isso seria lixo do usuário
+        let Some(slot) = live_block_containing(self, region, offset) else {
+            return Err(Diagnostic::DoubleFree { offset });
+        };
+        if offset != slot.index + HEADER {
+            return Err(Diagnostic::InteriorPointer { offset, block: slot.index + HEADER });
+        }
+        // O tamanho tem que ser o pedido exato (a sobra fica anotada no bloco)
+        let requested = self.requested(region, slot).size;
+        if layout.size() == requested {
+            Ok(())
+        } else {
+            Err(Diagnostic::SizeMismatch { offset, expected: requested, got: layout.size() })
+        }
+    }
+
+    fn resize(&mut self, region: Region, offset: usize, _layout: Layout, new_size: usize) -> Option<(usize, usize)> {
+        let block = self.block_at(region, offset)?;
+        let old = Self::size_of(region, block);
+        let need = Self::block_size(new_size)?;
+        if need <= old {
+            // Encolher: corta o fim (se sobrar um bloco mínimo)
+            let size = self.trim(region, block, old, need);
+            Self::set_slack(region, block, size, new_size);
+            return Some((old, size));
+        }
+        // Crescer: só se o vizinho de depois for livre e der conta
+        let next = block + old;
+        if next >= self.end || !Self::is_free(region, next) {
+            return None;
+        }
+        let next_size = Self::size_of(region, next);
+        if old + next_size < need {
+            return None;
+        }
+        self.remove(region, next, next_size);
+        self.set_block(region, block, old + next_size, false);
+        let size = self.trim(region, block, old + next_size, need);
+        Self::set_slack(region, block, size, new_size);
+        Some((old, size))
+    }
+
+    fn metadata_bytes(&self, region: Region) -> usize {
+        // Cabeçalhos contam dentro de cada bloco, só sobra o resto do fim
+        if self.ready {
+            region.len() - self.end
+        } else {
+            0
+        }
+    }
+
+    fn for_each_block(&self, region: Region, f: &mut dyn FnMut(Slot)) {
+        if !self.ready {
+            return;
+        }
+        let mut block = 0;
+        while block + MIN_BLOCK <= self.end {
+            let size = Self::size_of(region, block);
+            if !Self::is_free(region, block) {
+                f(Slot { size, index: block });
+            }
+            block += size;
+        }
+    }
+
+    fn requested(&self, region: Region, slot: Slot) -> Slot {
+        // O cabeçalho nunca é do pedido; a sobra do fim só existe com o bit
+        let mut size = slot.size - HEADER;
+        if Self::header(region, slot.index).size & SLACK_BIT != 0 {
+            size -= read_slack(region, slot.index + slot.size, size);
+        }
+        Slot { size, index: slot.index + HEADER }
+    }
+
+    fn for_each_gap(&self, region: Region, f: &mut dyn FnMut(usize, usize)) {
+        if !self.ready {
+            return f(0, region.len());
+        }
+        // Livres vizinhos sempre se juntam, então cada bloco livre é um buraco
+        // (com o cabeçalho dentro)
+        let mut block = 0;
+        while block + MIN_BLOCK <= self.end {
+            let size = Self::size_of(region, block);
+            if Self::is_free(region, block) {
+                f(block, block + size);
+            }
+            block += size;
+        }
+    }
+}
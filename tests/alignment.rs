@@ -0,0 +1,60 @@
+//! Pedidos com alinhamento grande: o ponteiro tem que sair alinhado e o
+//! bloco não pode gastar muito mais que o pedido.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Backend, Buddy, SlotTable, SpinLock, Tlsf};
+
+#[test]
+fn buddy_page_aligned_blocks_are_page_sized() {
+    static A: AlphaAlocator<{ 16 * 1024 }, 0, SpinLock, Buddy> = AlphaAlocator::new();
+    let layout = Layout::from_size_align(10, 4096).unwrap();
+    unsafe {
+        let a = A.alloc(layout);
+        let b = A.alloc(layout);
+        assert!(!a.is_null() && !b.is_null(), "{:?}", A.last_failure());
+        assert_eq!(a as usize % 4096, 0);
+        assert_eq!(b as usize % 4096, 0);
+        // Um bloco de 4 KiB pra cada, não 8 KiB
+        assert_eq!(A.stats().live_bytes, 2 * 4096);
+        A.dealloc(a, layout);
+        A.dealloc(b, layout);
+    }
+    assert_eq!(A.stats().live_blocks, 0);
+}
+
+/// Aloca vários blocos alinhados em 4096 com tamanhos variados, escreve neles
+/// todo e solta tudo.
+fn page_aligned<B: Backend + Send>(a: &AlphaAlocator<{ 64 * 1024 }, 0, SpinLock, B>) {
+    let layouts: Vec<_> = [1, 100, 4096, 5000].iter().map(|&size| Layout::from_size_align(size, 4096).unwrap()).collect();
+    unsafe {
+        let ptrs: Vec<_> = layouts.iter().map(|&layout| (a.alloc(layout), layout)).collect();
+        for &(ptr, layout) in &ptrs {
+            assert!(!ptr.is_null(), "{:?}", a.last_failure());
+            assert_eq!(ptr as usize % 4096, 0);
+            ptr.write_bytes(0xab, layout.size());
+        }
+        for (ptr, layout) in ptrs {
+            a.dealloc(ptr, layout);
+        }
+    }
+    assert_eq!(a.stats().live_blocks, 0);
+}
+
+#[test]
+fn page_alignment_slot_table() {
+    static A: AlphaAlocator<{ 64 * 1024 }, 0, SpinLock, SlotTable> = AlphaAlocator::new().with_checks();
+    page_aligned(&A);
+}
+
+#[test]
+fn page_alignment_buddy() {
+    static A: AlphaAlocator<{ 64 * 1024 }, 0, SpinLock, Buddy> = AlphaAlocator::new().with_checks();
+    page_aligned(&A);
+}
+
+#[test]
+fn page_alignment_tlsf() {
+    static A: AlphaAlocator<{ 64 * 1024 }, 0, SpinLock, Tlsf> = AlphaAlocator::new().with_checks();
+    page_aligned(&A);
+}
@@ -0,0 +1,337 @@
+//! Stress de concorrência: várias threads alocando, mudando de tamanho e
+//! soltando no mesmo alocador ao mesmo tempo.
+//!
+//! Cada bloco vivo fica registrado numa lista compartilhada (entra depois que
+//! o `alloc` volta e sai antes do `dealloc`), e todo bloco novo é conferido
+//! contra ela: se o alocador entregar uma faixa que encosta em outra viva, o
+//! teste quebra na hora. Além disso cada thread enche os blocos dela com uma
+//! marca e confere antes de soltar, e no fim tudo tem que ter voltado pro
+//! `free` (um pedido quase do tamanho da memória inteira tem que passar).
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::Mutex;
+use std::thread;
+
+use alocator::{
+    AlphaAlocator, Backend, Buddy, Diagnostic, EventKind, Outcome, PlacementPolicy, SlotTable, SpinLock, Tlsf,
+};
+
+const MEM: usize = 1 << 17;
+const THREADS: usize = 8;
+const ROUNDS: usize = 3000;
+// Blocos vivos por thread, no máximo
+const LIVE: usize = 16;
+
+type Alocador<B, const ARENAS: usize = 1> = AlphaAlocator<MEM, 0, SpinLock, B, ARENAS>;
+
+/// Faixas `[início, fim)` (endereços) dos blocos vivos de todas as threads.
+struct Registry(Mutex<Vec<(usize, usize)>>);
+
+impl Registry {
+    fn insert(&self, ptr: *mut u8, size: usize) {
+        let (start, end) = (ptr as usize, ptr as usize + size);
+        let mut live = self.0.lock().unwrap();
+        for &(s, e) in live.iter() {
+            assert!(end <= s || start >= e, "bloco [{start:#x}, {end:#x}) sobrepõe [{s:#x}, {e:#x})");
+        }
+        live.push((start, end));
+    }
+
+    fn remove(&self, ptr: *mut u8) {
+        let mut live = self.0.lock().unwrap();
+        let i = live.iter().position(|&(s, _)| s == ptr as usize).unwrap();
+        live.swap_remove(i);
+    }
+}
+
+fn panic_sink(diagnostic: &Diagnostic) {
+    panic!("diagnóstico inesperado: {diagnostic}");
+}
+
+/// xorshift, só pra não depender de crate de random
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as usize
+    }
+}
+
+fn check_tag(ptr: *mut u8, size: usize, tag: u8) {
+    for i in 0..size {
+        assert_eq!(unsafe { *ptr.add(i) }, tag, "bloco {ptr:?} corrompido no byte {i}");
+    }
+}
+
+fn worker<B: Backend + Send, const ARENAS: usize>(a: &Alocador<B, ARENAS>, registry: &Registry, id: usize) {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15 ^ (id as u64 + 1));
+    let mut live: Vec<(*mut u8, Layout, u8)> = Vec::new();
+
+    for round in 0..ROUNDS {
+        let tag = (id * 31 + round) as u8;
+        let action = rng.next() % 4;
+        if live.len() < LIVE && action < 2 {
+            let size = 1 + rng.next() % 300;
+            let align = 1 << (rng.next() % 7);
+            let layout = Layout::from_size_align(size, align).unwrap();
+            let ptr = unsafe {
+                if action == 0 {
+                    a.alloc(layout)
+                } else {
+                    a.alloc_zeroed(layout)
+                }
+            };
+            if ptr.is_null() {
+                continue;
+            }
+            assert_eq!(ptr as usize % align, 0);
+            registry.insert(ptr, size);
+            if action == 1 {
+                check_tag(ptr, size, 0);
+            }
+            unsafe { ptr.write_bytes(tag, size) };
+            live.push((ptr, layout, tag));
+        } else if !live.is_empty() {
+            let (ptr, layout, old_tag) = live.swap_remove(rng.next() % live.len());
+            check_tag(ptr, layout.size(), old_tag);
+            registry.remove(ptr);
+            if action == 2 {
+                let new_size = 1 + rng.next() % 400;
+                let new_ptr = unsafe { a.realloc(ptr, layout, new_size) };
+                if new_ptr.is_null() {
+                    // Falhou: o bloco antigo continua nosso
+                    registry.insert(ptr, layout.size());
+                    live.push((ptr, layout, old_tag));
+                    continue;
+                }
+                registry.insert(new_ptr, new_size);
+                check_tag(new_ptr, layout.size().min(new_size), old_tag);
+                unsafe { new_ptr.write_bytes(tag, new_size) };
+                live.push((new_ptr, Layout::from_size_align(new_size, layout.align()).unwrap(), tag));
+            } else {
+                unsafe { a.dealloc(ptr, layout) };
+            }
+        }
+    }
+
+    for (ptr, layout, tag) in live {
+        check_tag(ptr, layout.size(), tag);
+        registry.remove(ptr);
+        unsafe { a.dealloc(ptr, layout) };
+    }
+    // Fora do unix o cache não volta sozinho quando a thread termina
+    #[cfg(all(feature = "std", not(unix)))]
+    a.flush_thread_cache();
+}
+
+/// Roda as threads e confere que tudo voltou: nenhum bloco sobrou (fora o
+/// último run de cada classe pequena, que fica) e cabe um bloco de `big`.
+fn stress<B: Backend + Send, const ARENAS: usize>(a: &Alocador<B, ARENAS>, big: usize) {
+    a.set_diagnostic_sink(panic_sink);
+    let registry = Registry(Mutex::new(Vec::new()));
+    thread::scope(|s| {
+        let workers: Vec<_> = (0..THREADS)
+            .map(|id| {
+                let registry = &registry;
+                s.spawn(move || worker(a, registry, id))
+            })
+            .collect();
+        // O `join` espera a thread terminar de verdade (com o cache já
+        // esvaziado), o fim do `scope` só espera a closure
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    });
+    assert!(registry.0.lock().unwrap().is_empty());
+
+    let layout = Layout::from_size_align(big, 8).unwrap();
+    unsafe {
+        let ptr = a.alloc(layout);
+        assert!(!ptr.is_null(), "sobrou memória presa: {:?}", a.last_failure());
+        a.dealloc(ptr, layout);
+    }
+}
+
+/// Blocos vivos, conferindo que o `stats` conta igual.
+fn live_blocks<B: Backend + Send, const ARENAS: usize>(a: &Alocador<B, ARENAS>) -> usize {
+    let stats = a.stats();
+    let mut n = 0;
+    a.for_each_slot(|_| n += 1);
+    assert_eq!(stats.live_blocks, n);
+    if n == 0 {
+        assert_eq!(stats.live_bytes, 0);
+    }
+    assert!(stats.peak_bytes >= stats.live_bytes + stats.metadata_bytes);
+    n
+}
+
+#[test]
+fn slot_table_first_fit() {
+    static A: Alocador<SlotTable> = AlphaAlocator::new();
+    stress(&A, MEM - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn slot_table_next_fit() {
+    static A: Alocador<SlotTable> = AlphaAlocator::with_policy(PlacementPolicy::NextFit);
+    stress(&A, MEM - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn slot_table_best_fit_checked() {
+    static A: Alocador<SlotTable> = AlphaAlocator::with_policy(PlacementPolicy::BestFit).with_checks();
+    stress(&A, MEM - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn slot_table_size_classes() {
+    static A: Alocador<SlotTable> = AlphaAlocator::new().with_size_classes();
+    // O último run de cada classe fica, e pode ter ficado no meio da memória
+    stress(&A, MEM / 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn slot_table_thread_cache() {
+    // Safety: é um static
+    static A: Alocador<SlotTable> = unsafe { AlphaAlocator::new().with_thread_cache() };
+    stress(&A, MEM - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn tlsf_thread_cache_size_classes() {
+    // Safety: é um static
+    static A: Alocador<Tlsf> = unsafe { AlphaAlocator::new().with_size_classes().with_thread_cache() };
+    stress(&A, MEM / 2);
+}
+
+#[cfg(all(feature = "std", unix))]
+#[test]
+fn thread_cache_returns_on_thread_exit() {
+    // Safety: é um static
+    static A: Alocador<SlotTable> = unsafe { AlphaAlocator::new().with_thread_cache() };
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    for _ in 0..16 {
+        // Nenhuma chama o `flush_thread_cache`
+        thread::spawn(move || {
+            let ptrs: Vec<_> = (0..40).map(|_| unsafe { A.alloc(layout) }).collect();
+            for ptr in ptrs {
+                assert!(!ptr.is_null());
+                unsafe { A.dealloc(ptr, layout) };
+            }
+        })
+        .join()
+        .unwrap();
+    }
+    assert_eq!(A.stats().live_bytes, 0);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn buddy() {
+    static A: Alocador<Buddy> = AlphaAlocator::new();
+    // Os blocos são alinhados pelo endereço, então dependendo de onde a
+    // memória caiu o maior bloco inteiro pode ser só um quarto dela
+    stress(&A, MEM / 4);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn tlsf() {
+    static A: Alocador<Tlsf> = AlphaAlocator::new().with_checks();
+    stress(&A, MEM - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn slot_table_four_arenas() {
+    static A: Alocador<SlotTable, 4> = AlphaAlocator::new().with_checks();
+    // Cada arena tem só um quarto da memória
+    stress(&A, MEM / 4 - 256);
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn tlsf_four_arenas_size_classes() {
+    static A: Alocador<Tlsf, 4> = AlphaAlocator::new().with_size_classes();
+    stress(&A, MEM / 8);
+}
+
+#[test]
+fn alloc_in_picks_the_arena() {
+    static A: Alocador<Buddy, 4> = AlphaAlocator::new();
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        for arena in 0..A.arenas() {
+            let ptr = A.alloc_in(arena, layout);
+            assert!(!ptr.is_null());
+            assert_eq!(A.arena_of(ptr), Some(arena));
+            A.dealloc(ptr, layout);
+        }
+        assert!(A.alloc_in(A.arenas(), layout).is_null());
+    }
+    assert_eq!(live_blocks(&A), 0);
+}
+
+#[test]
+fn event_log_follows_the_real_order() {
+    // Cabe tudo que as threads fazem, sem o log dar a volta
+    static A: AlphaAlocator<MEM, 32768, SpinLock, SlotTable> = AlphaAlocator::new().with_size_classes();
+    thread::scope(|s| {
+        for id in 0..THREADS as u64 {
+            s.spawn(move || {
+                let mut rng = Rng(id + 1);
+                let mut live: Vec<(*mut u8, Layout)> = Vec::new();
+                for _ in 0..ROUNDS / 2 {
+                    if live.len() < 8 && rng.next().is_multiple_of(2) {
+                        let layout = Layout::from_size_align(1 + rng.next() % 200, 8).unwrap();
+                        let ptr = unsafe { A.alloc(layout) };
+                        if !ptr.is_null() {
+                            live.push((ptr, layout));
+                        }
+                    } else if !live.is_empty() {
+                        let (ptr, layout) = live.swap_remove(rng.next() % live.len());
+                        if rng.next().is_multiple_of(2) {
+                            let new_size = 1 + rng.next() % 200;
+                            let new_ptr = unsafe { A.realloc(ptr, layout, new_size) };
+                            assert!(!new_ptr.is_null());
+                            live.push((new_ptr, Layout::from_size_align(new_size, 8).unwrap()));
+                        } else {
+                            unsafe { A.dealloc(ptr, layout) };
+                        }
+                    }
+                }
+                for (ptr, layout) in live {
+                    unsafe { A.dealloc(ptr, layout) };
+                }
+            });
+        }
+    });
+
+    // Repetindo o log na ordem do `seq`, nenhum bloco pode nascer num offset
+    // que ainda tá vivo nem morrer sem ter nascido
+    let mut live = std::collections::HashSet::new();
+    let mut count = 0;
+    for event in A.events() {
+        assert_eq!(event.seq, count, "log com buraco");
+        count += 1;
+        assert_eq!(event.outcome, Outcome::Ok, "{event}");
+        match event.kind {
+            EventKind::Alloc | EventKind::AllocZeroed => assert!(live.insert(event.offset.unwrap()), "{event}"),
+            EventKind::Dealloc => assert!(live.remove(&event.offset.unwrap()), "{event}"),
+            EventKind::Realloc { old_offset, .. } => {
+                assert!(live.remove(&old_offset.unwrap()), "{event}");
+                assert!(live.insert(event.offset.unwrap()), "{event}");
+            }
+        }
+    }
+    assert!(count > 0 && live.is_empty());
+}
@@ -0,0 +1,61 @@
+//! Casos de borda do `GlobalAlloc`: bloco no offset 0 e pedidos de tamanho
+//! zero (ponteiro pendurado, que não ocupa nada).
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Diagnostic, Outcome, SlotTable, SpinLock};
+
+type Alocador = AlphaAlocator<{ 16 * 1024 }, 16, SpinLock, SlotTable>;
+
+fn panic_sink(diagnostic: &Diagnostic) {
+    panic!("diagnóstico inesperado: {diagnostic}");
+}
+
+#[test]
+fn dealloc_at_offset_zero() {
+    static A: Alocador = AlphaAlocator::new().with_checks();
+    A.set_diagnostic_sink(panic_sink);
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    unsafe {
+        // Memória vazia: o primeiro bloco cai bem no começo
+        let ptr = A.alloc(layout);
+        assert_eq!(A.identify_adress(ptr), Some(0));
+        A.dealloc(ptr, layout);
+        assert_eq!(A.events().last().unwrap().outcome, Outcome::Ok);
+        assert_eq!(A.stats().live_blocks, 0);
+
+        // E o mesmo lugar volta a ser entregue
+        let again = A.alloc(layout);
+        assert_eq!(again, ptr);
+        A.dealloc(again, layout);
+    }
+    assert_eq!(A.stats().live_blocks, 0);
+}
+
+#[test]
+fn zero_size_is_dangling() {
+    static A: Alocador = AlphaAlocator::new().with_checks();
+    A.set_diagnostic_sink(panic_sink);
+    for align in [1, 8, 64, 4096] {
+        let layout = Layout::from_size_align(0, align).unwrap();
+        unsafe {
+            let ptr = A.alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % align, 0);
+            // Fora da memória e sem bloco nenhum
+            assert_eq!(A.identify_adress(ptr), None);
+            assert_eq!(A.stats().live_blocks, 0);
+            A.dealloc(ptr, layout);
+
+            // Crescer a partir do pendurado vira um bloco de verdade...
+            let grown = A.realloc(ptr, layout, 40);
+            assert!(A.identify_adress(grown).is_some());
+            assert_eq!(A.stats().live_blocks, 1);
+            // ...e voltar pra zero solta ele
+            let shrunk = A.realloc(grown, Layout::from_size_align(40, align).unwrap(), 0);
+            assert_eq!(A.identify_adress(shrunk), None);
+            assert_eq!(A.stats().live_blocks, 0);
+        }
+    }
+    assert_eq!(A.stats().live_bytes, 0);
+}
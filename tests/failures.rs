@@ -0,0 +1,91 @@
+//! O que fica guardado quando o `alloc` devolve null, e o `with_memory_limit`
+//! (tem que faltar memória igual numa memória menor).
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AllocFailure, AlphaAlocator, Backend, Buddy, SlotTable, SpinLock, Tlsf};
+
+const TOO_BIG: usize = 1 << 20;
+
+fn fail<B: Backend + Send>(a: &AlphaAlocator<4096, 0, SpinLock, B>) {
+    let layout = Layout::from_size_align(TOO_BIG, 8).unwrap();
+    assert!(unsafe { a.alloc(layout) }.is_null());
+    assert_eq!(a.last_failure(), Some(AllocFailure::OutOfMemory));
+    assert_eq!(a.stats().failures.out_of_memory, 1);
+}
+
+#[test]
+fn snapshot_is_opt_in_where_free_memory_is_written() {
+    // O TLSF escreve nos buracos: a foto só com o `with_failure_snapshots`
+    static A: AlphaAlocator<4096, 0, SpinLock, Tlsf> = AlphaAlocator::new();
+    fail(&A);
+    assert_eq!(A.last_failure_snapshot(), None);
+
+    static B: AlphaAlocator<4096, 0, SpinLock, Tlsf> = AlphaAlocator::new().with_failure_snapshots();
+    fail(&B);
+    assert_eq!(B.last_failure_snapshot().unwrap().fragmentation, B.fragmentation());
+}
+
+#[test]
+fn snapshot_can_be_turned_off() {
+    static A: AlphaAlocator<4096, 0, SpinLock, SlotTable> = AlphaAlocator::new().without_failure_snapshots();
+    fail(&A);
+    assert_eq!(A.last_failure_snapshot(), None);
+}
+
+#[test]
+fn snapshot_describes_the_memory() {
+    // A tabela de slots não escreve nos buracos, então a foto já vem ligada
+    static A: AlphaAlocator<4096, 0> = AlphaAlocator::new();
+    let small = Layout::from_size_align(100, 8).unwrap();
+    let ptr = unsafe { A.alloc(small) };
+    let layout = Layout::from_size_align(TOO_BIG, 16).unwrap();
+    assert!(unsafe { A.alloc(layout) }.is_null());
+
+    let snapshot = A.last_failure_snapshot().unwrap();
+    assert_eq!(snapshot.reason, AllocFailure::OutOfMemory);
+    assert_eq!((snapshot.size, snapshot.align), (TOO_BIG, 16));
+    assert_eq!(snapshot.fragmentation, A.fragmentation());
+    assert!(snapshot.fragmentation.free_bytes > 0 && snapshot.fragmentation.free_bytes < 4096);
+    unsafe { A.dealloc(ptr, small) };
+}
+
+#[test]
+fn snapshot_counts_every_arena_tried() {
+    static A: AlphaAlocator<4096, 0, SpinLock, Buddy, 2> = AlphaAlocator::new().with_failure_snapshots();
+    let small = Layout::from_size_align(100, 8).unwrap();
+    let ptr = unsafe { A.alloc(small) };
+    assert!(unsafe { A.alloc(Layout::from_size_align(TOO_BIG, 8).unwrap()) }.is_null());
+    assert_eq!(A.last_failure_snapshot().unwrap().fragmentation, A.fragmentation());
+    unsafe { A.dealloc(ptr, small) };
+}
+
+#[test]
+fn memory_limit_acts_like_a_smaller_memory() {
+    static A: AlphaAlocator<8192, 0> = AlphaAlocator::new().with_memory_limit(4096);
+    let fits = Layout::from_size_align(3000, 8).unwrap();
+    let too_big = Layout::from_size_align(5000, 8).unwrap();
+    unsafe {
+        assert!(A.alloc(too_big).is_null());
+        let ptr = A.alloc(fits);
+        assert!(!ptr.is_null(), "{:?}", A.last_failure());
+        assert!(A.fragmentation().free_bytes < 4096 - 3000);
+        // O que fica depois do limite nem conta como memória do alocador
+        assert_eq!(A.identify_adress(ptr.add(4096)), None);
+        A.dealloc(ptr, fits);
+    }
+}
+
+#[test]
+fn memory_limit_splits_the_arenas() {
+    static A: AlphaAlocator<8192, 0, SpinLock, Buddy, 2> = AlphaAlocator::new().with_memory_limit(4096);
+    let layout = Layout::from_size_align(512, 8).unwrap();
+    unsafe {
+        for arena in 0..2 {
+            let ptr = A.alloc_in(arena, layout);
+            assert!(!ptr.is_null(), "{:?}", A.last_failure());
+            assert_eq!(A.arena_of(ptr), Some(arena));
+            assert!(A.identify_adress(ptr).unwrap() < 4096);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! `Fragmentation`: histograma dos buracos e a razão de fragmentação externa.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, SlotTable, SpinLock, GAP_BUCKETS};
+
+const MEM: usize = 4096;
+
+type Alocador = AlphaAlocator<MEM, 0, SpinLock, SlotTable>;
+
+fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 1).unwrap()
+}
+
+#[test]
+fn histogram_buckets_by_power_of_two() {
+    static A: Alocador = AlphaAlocator::new();
+    // Buracos de 300 (em 0), 50 (em 400), 200 (em 500) e o resto depois de 800
+    let sizes = [300, 100, 50, 50, 200, 100];
+    let ptrs: Vec<_> = sizes.iter().map(|&size| unsafe { A.alloc(layout(size)) }).collect();
+    for i in [0, 2, 4] {
+        unsafe { A.dealloc(ptrs[i], layout(sizes[i])) };
+    }
+
+    let tail = MEM - A.stats().metadata_bytes - 800;
+    let fragmentation = A.fragmentation();
+    assert_eq!(fragmentation.gaps, 4);
+    assert_eq!(fragmentation.free_bytes, 300 + 50 + 200 + tail);
+    assert_eq!(fragmentation.largest_gap, tail);
+
+    let mut histogram = [0; GAP_BUCKETS];
+    // 50 fica em [32, 64), 200 em [128, 256), 300 em [256, 512)
+    histogram[5] += 1;
+    histogram[7] += 1;
+    histogram[8] += 1;
+    histogram[tail.ilog2() as usize] += 1;
+    assert_eq!(fragmentation.histogram, histogram);
+
+    let ratio = 1.0 - tail as f32 / fragmentation.free_bytes as f32;
+    assert!((fragmentation.ratio() - ratio).abs() < 1e-6, "{}", fragmentation.ratio());
+}
+
+#[test]
+fn ratio_is_zero_without_splinters() {
+    static A: Alocador = AlphaAlocator::new();
+    // Memória vazia: um buraco só
+    assert_eq!(A.fragmentation().gaps, 1);
+    assert_eq!(A.fragmentation().ratio(), 0.0);
+
+    // Bloco no começo: o livre continua num buraco só
+    let ptr = unsafe { A.alloc(layout(1000)) };
+    assert_eq!(A.fragmentation().gaps, 1);
+    assert_eq!(A.fragmentation().ratio(), 0.0);
+
+    // Tudo ocupado: sem livre nenhum também dá 0
+    let rest = MEM - A.stats().metadata_bytes - 1000;
+    let full = unsafe { A.alloc(layout(rest)) };
+    assert!(!full.is_null(), "{:?}", A.last_failure());
+    let fragmentation = A.fragmentation();
+    assert_eq!((fragmentation.free_bytes, fragmentation.gaps), (0, 0));
+    assert_eq!(fragmentation.histogram, [0; GAP_BUCKETS]);
+    assert_eq!(fragmentation.ratio(), 0.0);
+
+    unsafe {
+        A.dealloc(ptr, layout(1000));
+        A.dealloc(full, layout(rest));
+    }
+}
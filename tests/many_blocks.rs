@@ -0,0 +1,105 @@
+//! Muitos blocos vivos ao mesmo tempo: a tabela de slots não tem mais limite
+//! fixo (era 100), então ela tem que crescer (mudando de lugar se um bloco
+//! encostar nela) e depois devolver a memória.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, SlotTable, SpinLock};
+
+const MEM: usize = 64 * 1024;
+const BLOCKS: usize = 1000;
+
+#[test]
+fn slot_table_holds_more_than_100_blocks() {
+    static A: AlphaAlocator<MEM, 0, SpinLock, SlotTable> = AlphaAlocator::new().with_checks();
+    let layout = Layout::from_size_align(24, 8).unwrap();
+    unsafe {
+        let ptrs: Vec<_> = (0..BLOCKS).map(|_| A.alloc(layout)).collect();
+        assert!(ptrs.iter().all(|p| !p.is_null()), "{:?}", A.last_failure());
+        for (i, &ptr) in ptrs.iter().enumerate() {
+            ptr.write_bytes(i as u8, layout.size());
+        }
+        assert_eq!(A.stats().live_blocks, BLOCKS);
+
+        // Solta os pares primeiro (buracos no meio da tabela) e depois o resto
+        for (i, &ptr) in ptrs.iter().enumerate() {
+            assert!((0..layout.size()).all(|k| *ptr.add(k) == i as u8), "bloco {i} corrompido");
+            if i % 2 == 0 {
+                A.dealloc(ptr, layout);
+            }
+        }
+        assert_eq!(A.stats().live_blocks, BLOCKS / 2);
+        for &ptr in ptrs.iter().skip(1).step_by(2) {
+            A.dealloc(ptr, layout);
+        }
+    }
+    assert_eq!(A.stats().live_blocks, 0);
+
+    // A tabela encolheu de volta: cabe um bloco quase do tamanho da memória
+    let big = Layout::from_size_align(MEM - 256, 8).unwrap();
+    unsafe {
+        let ptr = A.alloc(big);
+        assert!(!ptr.is_null(), "{:?}", A.last_failure());
+        A.dealloc(ptr, big);
+    }
+}
+
+#[test]
+fn slot_table_moves_when_a_block_touches_it() {
+    static A: AlphaAlocator<4096, 0, SpinLock, SlotTable> = AlphaAlocator::new().with_checks();
+    let first = Layout::from_size_align(1024, 8).unwrap();
+    let small = Layout::from_size_align(8, 8).unwrap();
+    unsafe {
+        let a = A.alloc(first);
+        // O resto até a tabela (que começa com 8 entradas no topo)
+        let rest = Layout::from_size_align(4096 - 1024 - 8 * 8, 8).unwrap();
+        let b = A.alloc(rest);
+        assert!(!a.is_null() && !b.is_null(), "{:?}", A.last_failure());
+        b.write_bytes(0xAB, rest.size());
+        A.dealloc(a, first);
+
+        // Bem mais que 8 blocos: a tabela tem que sair de cima do `b`
+        let ptrs: Vec<_> = (0..50).map(|_| A.alloc(small)).collect();
+        assert!(ptrs.iter().all(|p| !p.is_null()), "{:?}", A.last_failure());
+        for (i, &ptr) in ptrs.iter().enumerate() {
+            ptr.write_bytes(i as u8, small.size());
+        }
+        for (i, &ptr) in ptrs.iter().enumerate() {
+            assert!((0..small.size()).all(|k| *ptr.add(k) == i as u8), "bloco {i} corrompido");
+            A.dealloc(ptr, small);
+        }
+        assert!((0..rest.size()).all(|k| *b.add(k) == 0xAB));
+        A.dealloc(b, rest);
+    }
+    assert_eq!(A.stats().live_blocks, 0);
+
+    // A tabela voltou pro topo: cabe um bloco quase do tamanho da memória
+    let big = Layout::from_size_align(4096 - 8 * 8, 8).unwrap();
+    unsafe {
+        let ptr = A.alloc(big);
+        assert!(!ptr.is_null(), "{:?}", A.last_failure());
+        A.dealloc(ptr, big);
+    }
+}
+
+#[test]
+fn slot_table_moves_after_a_realloc_reaches_it() {
+    static A: AlphaAlocator<4096, 0, SpinLock, SlotTable> = AlphaAlocator::new();
+    let (first, layout) = (Layout::from_size_align(512, 8).unwrap(), Layout::from_size_align(64, 8).unwrap());
+    unsafe {
+        let a = A.alloc(first);
+        let b = A.alloc(layout);
+        // Cresce o `b` no lugar até encostar na tabela
+        let grown = 4096 - 512 - 8 * 8;
+        let b2 = A.realloc(b, layout, grown);
+        assert_eq!(b2, b);
+        A.dealloc(a, first);
+        let ptrs: Vec<_> = (0..12).map(|_| A.alloc(Layout::from_size_align(4, 4).unwrap())).collect();
+        assert!(ptrs.iter().all(|p| !p.is_null()), "{:?}", A.last_failure());
+        for ptr in ptrs {
+            A.dealloc(ptr, Layout::from_size_align(4, 4).unwrap());
+        }
+        A.dealloc(b2, Layout::from_size_align(grown, 8).unwrap());
+    }
+    assert_eq!(A.stats().live_blocks, 0);
+}
@@ -0,0 +1,64 @@
+//! `dump_map`: a sobra dentro dos blocos aparece no mapa e na tabela.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Backend, Buddy, SlotTable, SpinLock, Tlsf, MAP_OVERHEAD, MAP_USED};
+
+/// Pede esses tamanhos, muda o primeiro pra `resized` e devolve o mapa e a
+/// coluna de sobra da tabela, na ordem dos pedidos.
+fn slack<B: Backend + Send>(
+    a: &AlphaAlocator<4096, 0, SpinLock, B>,
+    sizes: &[usize],
+    resized: usize,
+) -> (String, Vec<usize>) {
+    let layouts: Vec<_> = sizes.iter().map(|&size| Layout::from_size_align(size, 8).unwrap()).collect();
+    unsafe {
+        let mut ptrs: Vec<_> = layouts.iter().map(|&l| a.alloc(l)).collect();
+        assert!(ptrs.iter().all(|p| !p.is_null()), "{:?}", a.last_failure());
+        ptrs[0] = a.realloc(ptrs[0], layouts[0], resized);
+
+        let mut map = String::new();
+        a.dump_map(&mut map, 4).unwrap();
+        let rows: Vec<usize> = map
+            .lines()
+            .skip_while(|line| !line.trim_start().starts_with("offset"))
+            .filter_map(|line| line.split_whitespace().nth(3)?.parse().ok())
+            .collect();
+        assert_eq!(rows.len(), sizes.len(), "{}", map);
+        // A tabela vem em ordem de offset, igual os ponteiros
+        let mut by_addr: Vec<_> = (0..ptrs.len()).collect();
+        by_addr.sort_by_key(|&i| ptrs[i] as usize);
+        let mut slack = vec![0; sizes.len()];
+        for (&row, &i) in rows.iter().zip(&by_addr) {
+            slack[i] = row;
+        }
+
+        a.dealloc(ptrs[0], Layout::from_size_align(resized, 8).unwrap());
+        for (&ptr, &layout) in ptrs.iter().zip(&layouts).skip(1) {
+            a.dealloc(ptr, layout);
+        }
+        (map, slack)
+    }
+}
+
+#[test]
+fn slot_table_has_no_slack() {
+    static A: AlphaAlocator<4096, 0, SpinLock, SlotTable> = AlphaAlocator::new();
+    assert_eq!(slack(&A, &[100, 30], 60).1, [0, 0]);
+}
+
+#[test]
+fn buddy_slack_is_the_rounding() {
+    static A: AlphaAlocator<4096, 0, SpinLock, Buddy> = AlphaAlocator::new();
+    // 120 continua no bloco de 128 e 700 vai pro de 1024 (sobra que não cabe num byte)
+    let (map, slack) = slack(&A, &[100, 700], 120);
+    assert_eq!(slack, [8, 324]);
+    assert!(map.contains(&format!("{}{}", MAP_USED, MAP_OVERHEAD)), "{}", map);
+}
+
+#[test]
+fn tlsf_slack_counts_the_header() {
+    static A: AlphaAlocator<4096, 0, SpinLock, Tlsf> = AlphaAlocator::new();
+    // Encolher pra 40 corta o fim: só o cabeçalho sobra; 300 arredonda pra 304
+    assert_eq!(slack(&A, &[100, 300], 40).1, [8, 12]);
+}
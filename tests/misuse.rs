@@ -0,0 +1,104 @@
+//! Modo checado: `dealloc` errado (double free, ponteiro do meio, tamanho
+//! trocado, desalinhado) tem que ser recusado com o diagnóstico certo, em
+//! todos os backends, sem soltar nada.
+//!
+//! O diagnóstico é lido do log de eventos (o mesmo que vai pro sink), assim
+//! cada teste olha só o alocador dele.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Backend, Buddy, Diagnostic, Outcome, SlotTable, SpinLock, Tlsf};
+
+type Alocador<B> = AlphaAlocator<{ 16 * 1024 }, 16, SpinLock, B>;
+
+fn quiet_sink(_: &Diagnostic) {}
+
+/// Diagnóstico do último evento do log (que tem que ter sido recusado).
+fn last_rejection<B: Backend + Send>(a: &Alocador<B>) -> Diagnostic {
+    match a.events().last().map(|event| event.outcome) {
+        Some(Outcome::Rejected(diagnostic)) => diagnostic,
+        other => panic!("esperava um evento recusado, veio {other:?}"),
+    }
+}
+
+fn misuse<B: Backend + Send>(a: &Alocador<B>) {
+    a.set_diagnostic_sink(quiet_sink);
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    unsafe {
+        let ptr = a.alloc(layout);
+        assert!(!ptr.is_null());
+        let offset = a.identify_adress(ptr).unwrap();
+
+        // Ponteiro do meio do bloco
+        a.dealloc(ptr.add(16), Layout::from_size_align(48, 8).unwrap());
+        assert_eq!(last_rejection(a), Diagnostic::InteriorPointer { offset: offset + 16, block: offset });
+
+        // Tamanho que não bate com o bloco
+        a.dealloc(ptr, Layout::from_size_align(200, 8).unwrap());
+        match last_rejection(a) {
+            Diagnostic::SizeMismatch { offset: o, got: 200, .. } => assert_eq!(o, offset),
+            other => panic!("esperava SizeMismatch, veio {other:?}"),
+        }
+
+        // Ponteiro desalinhado pro layout
+        a.dealloc(ptr.add(1), layout);
+        assert_eq!(last_rejection(a), Diagnostic::Misaligned { offset: offset + 1, align: 8 });
+
+        // Nada disso soltou o bloco: o dealloc certo passa, o segundo não
+        ptr.write_bytes(0x5a, layout.size());
+        a.dealloc(ptr, layout);
+        assert_eq!(a.events().last().unwrap().outcome, Outcome::Ok);
+        a.dealloc(ptr, layout);
+        assert_eq!(last_rejection(a), Diagnostic::DoubleFree { offset });
+    }
+}
+
+/// Tamanho trocado que cai no mesmo arredondamento do backend (o buddy põe
+/// 65 e 100 no mesmo bloco de 128, o TLSF arredonda de 8 em 8): o check
+/// compara com o pedido exato, não com o bloco.
+fn size_inside_rounding<B: Backend + Send>(a: &Alocador<B>) {
+    a.set_diagnostic_sink(quiet_sink);
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        let ptr = a.alloc(layout);
+        assert!(!ptr.is_null());
+        let offset = a.identify_adress(ptr).unwrap();
+        for size in [65, 104] {
+            a.dealloc(ptr, Layout::from_size_align(size, 8).unwrap());
+            assert_eq!(last_rejection(a), Diagnostic::SizeMismatch { offset, expected: 100, got: size });
+        }
+        a.dealloc(ptr, layout);
+        assert_eq!(a.events().last().unwrap().outcome, Outcome::Ok);
+    }
+    assert_eq!(a.stats().live_blocks, 0);
+}
+
+#[test]
+fn slot_table() {
+    static A: Alocador<SlotTable> = AlphaAlocator::new().with_checks();
+    misuse(&A);
+    assert_eq!(A.stats().live_blocks, 0);
+    size_inside_rounding(&A);
+}
+
+#[test]
+fn slot_table_size_classes() {
+    static A: Alocador<SlotTable> = AlphaAlocator::new().with_size_classes().with_checks();
+    misuse(&A);
+}
+
+#[test]
+fn buddy() {
+    static A: Alocador<Buddy> = AlphaAlocator::new().with_checks();
+    misuse(&A);
+    assert_eq!(A.stats().live_blocks, 0);
+    size_inside_rounding(&A);
+}
+
+#[test]
+fn tlsf() {
+    static A: Alocador<Tlsf> = AlphaAlocator::new().with_checks();
+    misuse(&A);
+    assert_eq!(A.stats().live_blocks, 0);
+    size_inside_rounding(&A);
+}
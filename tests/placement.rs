@@ -0,0 +1,68 @@
+//! O buraco que cada `PlacementPolicy` escolhe, sempre no mesmo desenho de
+//! memória: buracos de 300 (em 0), 50 (em 400) e 200 (em 500) e o resto
+//! depois do último bloco (de 800 até a tabela).
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, PlacementPolicy, SlotTable, SpinLock};
+
+type Alocador = AlphaAlocator<4096, 0, SpinLock, SlotTable>;
+
+fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 1).unwrap()
+}
+
+/// Aloca em sequência e solta os blocos que viram os buracos.
+fn carve(a: &Alocador) {
+    let sizes = [300, 100, 50, 50, 200, 100];
+    let ptrs: Vec<_> = sizes.iter().map(|&size| unsafe { a.alloc(layout(size)) }).collect();
+    let offsets: Vec<_> = ptrs.iter().map(|&ptr| a.identify_adress(ptr).unwrap()).collect();
+    assert_eq!(offsets, [0, 300, 400, 450, 500, 700]);
+    for i in [0, 2, 4] {
+        unsafe { a.dealloc(ptrs[i], layout(sizes[i])) };
+    }
+}
+
+/// Offset de um pedido novo de `size` bytes.
+fn place(a: &Alocador, size: usize) -> usize {
+    let ptr = unsafe { a.alloc(layout(size)) };
+    a.identify_adress(ptr).unwrap()
+}
+
+#[test]
+fn first_fit_takes_the_first_gap() {
+    static A: Alocador = AlphaAlocator::with_policy(PlacementPolicy::FirstFit);
+    carve(&A);
+    assert_eq!(place(&A, 150), 0);
+    // Sobraram 150 em 150 e 50 em 400: o primeiro que cabe 200 é o de 500
+    assert_eq!(place(&A, 200), 500);
+}
+
+#[test]
+fn best_fit_takes_the_tightest_gap() {
+    static A: Alocador = AlphaAlocator::with_policy(PlacementPolicy::BestFit);
+    carve(&A);
+    assert_eq!(place(&A, 150), 500);
+    assert_eq!(place(&A, 40), 400);
+}
+
+#[test]
+fn worst_fit_takes_the_largest_gap() {
+    static A: Alocador = AlphaAlocator::with_policy(PlacementPolicy::WorstFit);
+    carve(&A);
+    assert_eq!(place(&A, 150), 800);
+    assert_eq!(place(&A, 40), 950);
+}
+
+#[test]
+fn next_fit_follows_the_cursor() {
+    static A: Alocador = AlphaAlocator::with_policy(PlacementPolicy::NextFit);
+    carve(&A);
+    // O cursor parou no fim do último bloco do `carve` (800), então os
+    // buracos de antes ficam pra depois da volta
+    assert_eq!(place(&A, 40), 800);
+    assert_eq!(place(&A, 150), 840);
+    assert_eq!(place(&A, 3000), 990);
+    // Depois do cursor (3990) não cabe mais: dá a volta e pega o primeiro
+    assert_eq!(place(&A, 150), 0);
+}
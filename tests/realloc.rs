@@ -0,0 +1,70 @@
+//! `realloc` no lugar: encolher e crescer pra dentro do buraco seguinte
+//! devolvem o mesmo ponteiro em todos os backends; só muda de lugar quando o
+//! vizinho tá ocupado.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Backend, Buddy, SlotTable, SpinLock, Tlsf};
+
+fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 8).unwrap()
+}
+
+unsafe fn fill(ptr: *mut u8, size: usize) {
+    for i in 0..size {
+        *ptr.add(i) = i as u8;
+    }
+}
+
+unsafe fn assert_filled(ptr: *mut u8, size: usize) {
+    for i in 0..size {
+        assert_eq!(*ptr.add(i), i as u8, "byte {i}");
+    }
+}
+
+fn resize_in_place<B: Backend + Send>(a: &AlphaAlocator<4096, 0, SpinLock, B>) {
+    unsafe {
+        let ptr = a.alloc(layout(256));
+        assert!(!ptr.is_null());
+        fill(ptr, 256);
+
+        // Encolher só corta o fim
+        assert_eq!(a.realloc(ptr, layout(256), 64), ptr);
+        assert_filled(ptr, 64);
+        // E o que foi cortado continua livre logo depois: cresce por cima
+        assert_eq!(a.realloc(ptr, layout(64), 128), ptr);
+        assert_eq!(a.realloc(ptr, layout(128), 256), ptr);
+        assert_filled(ptr, 64);
+
+        // Com um vizinho no caminho não tem como: muda de lugar e leva os dados
+        fill(ptr, 256);
+        assert_eq!(a.realloc(ptr, layout(256), 128), ptr);
+        let next = a.alloc(layout(128));
+        assert!(next > ptr && (next as usize) < ptr as usize + 256, "{ptr:?} {next:?}");
+        let moved = a.realloc(ptr, layout(128), 256);
+        assert!(!moved.is_null() && moved != ptr, "{:?}", a.last_failure());
+        assert_filled(moved, 128);
+
+        a.dealloc(moved, layout(256));
+        a.dealloc(next, layout(128));
+        assert_eq!(a.stats().live_bytes, 0);
+    }
+}
+
+#[test]
+fn slot_table_resizes_in_place() {
+    static A: AlphaAlocator<4096, 0, SpinLock, SlotTable> = AlphaAlocator::new();
+    resize_in_place(&A);
+}
+
+#[test]
+fn buddy_resizes_in_place() {
+    static A: AlphaAlocator<4096, 0, SpinLock, Buddy> = AlphaAlocator::new();
+    resize_in_place(&A);
+}
+
+#[test]
+fn tlsf_resizes_in_place() {
+    static A: AlphaAlocator<4096, 0, SpinLock, Tlsf> = AlphaAlocator::new();
+    resize_in_place(&A);
+}
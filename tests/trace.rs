@@ -0,0 +1,98 @@
+//! Formato de trace: todo evento tem que voltar igual depois de
+//! `write_trace` + `from_trace`, e linha estragada tem que dar erro.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AllocFailure, AlphaAlocator, Diagnostic, Event, EventKind, Outcome, TraceError};
+
+fn round_trip(event: Event) {
+    let mut line = String::new();
+    event.write_trace(&mut line).unwrap();
+    assert!(!line.contains('\n'), "{line}");
+    assert_eq!(Event::from_trace(&line), Ok(Some(event)), "{line}");
+}
+
+#[test]
+fn every_kind_and_outcome_round_trips() {
+    let kinds = [
+        EventKind::Alloc,
+        EventKind::AllocZeroed,
+        EventKind::Dealloc,
+        EventKind::Realloc { old_offset: Some(48), old_size: 16 },
+        EventKind::Realloc { old_offset: None, old_size: 0 },
+    ];
+    let outcomes = [
+        Outcome::Ok,
+        Outcome::Failed(AllocFailure::OutOfMemory),
+        Outcome::Failed(AllocFailure::Fragmentation),
+        Outcome::Failed(AllocFailure::SlotTableFull),
+        Outcome::Rejected(Diagnostic::UnknownSlot { offset: 7 }),
+        Outcome::Rejected(Diagnostic::OutsideArena { addr: usize::MAX - 3 }),
+        Outcome::Rejected(Diagnostic::DoubleFree { offset: 0 }),
+        Outcome::Rejected(Diagnostic::InteriorPointer { offset: 40, block: 32 }),
+        Outcome::Rejected(Diagnostic::SizeMismatch { offset: 32, expected: 24, got: 100 }),
+        Outcome::Rejected(Diagnostic::Misaligned { offset: 33, align: 16 }),
+    ];
+    let mut seq = 0;
+    for kind in kinds {
+        for outcome in outcomes {
+            for offset in [None, Some(0), Some(29_984)] {
+                round_trip(Event { seq, kind, offset, size: 24, align: 8, outcome });
+                seq += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn comments_and_blank_lines_are_skipped() {
+    for line in ["", "   ", "# alocator trace v1", "# mem 30000 arenas 1 policy FirstFit"] {
+        assert_eq!(Event::from_trace(line), Ok(None));
+    }
+}
+
+#[test]
+fn broken_lines_name_the_field() {
+    let cases = [
+        ("x alloc 8 8 0 ok", "seq"),
+        ("0", "op"),
+        ("0 free 8 8 0 ok", "op"),
+        ("0 realloc:a:8 8 8 0 ok", "op"),
+        ("0 alloc -8 8 0 ok", "size"),
+        ("0 alloc 8", "align"),
+        ("0 alloc 8 8 zero ok", "offset"),
+        ("0 alloc 8 8 0", "outcome"),
+        ("0 alloc 8 8 0 failed:tired", "outcome"),
+        ("0 dealloc 8 8 0 rejected:interior:1", "outcome"),
+        ("0 dealloc 8 8 0 rejected:size:1:2:3:4", "outcome"),
+        ("0 alloc 8 8 0 ok extra", "fim da linha"),
+    ];
+    for (line, field) in cases {
+        assert_eq!(Event::from_trace(line), Err(TraceError { field }), "{line}");
+    }
+}
+
+#[test]
+fn allocator_trace_reads_back_as_its_events() {
+    static A: AlphaAlocator<4096, 32> = AlphaAlocator::new().with_checks();
+    A.set_diagnostic_sink(|_| {});
+    unsafe {
+        let layout = Layout::from_size_align(40, 8).unwrap();
+        let a = A.alloc(layout);
+        let b = A.alloc_zeroed(layout);
+        let a = A.realloc(a, layout, 200);
+        A.dealloc(b, layout);
+        // Recusado e falhou também vão pro trace
+        A.dealloc(b, layout);
+        assert!(A.alloc(Layout::from_size_align(1 << 20, 8).unwrap()).is_null());
+        A.dealloc(a, Layout::from_size_align(200, 8).unwrap());
+    }
+
+    let mut text = String::new();
+    A.write_trace(&mut text).unwrap();
+    assert!(text.starts_with("# alocator trace v1\n"));
+    let parsed: Vec<Event> = text.lines().filter_map(|line| Event::from_trace(line).unwrap()).collect();
+    let events: Vec<Event> = A.events().collect();
+    assert_eq!(events.len(), 7);
+    assert_eq!(parsed, events);
+}
@@ -0,0 +1,80 @@
+//! `alloc_zeroed` com o high water mark: o que já foi entregue abaixo da
+//! marca tem que voltar zerado, e o que a tabela de slots devolve também
+//! (senão o pedaço acima da marca não vem zerado).
+
+use std::alloc::{GlobalAlloc, Layout};
+
+use alocator::{AlphaAlocator, Backend, Buddy, SlotTable, SpinLock, Tlsf};
+
+const MEM: usize = 4096;
+
+fn reused_block_is_zeroed<B: Backend + Send>(a: &AlphaAlocator<MEM, 0, SpinLock, B>) {
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        let ptr = a.alloc(layout);
+        ptr.write_bytes(0xAB, layout.size());
+        a.dealloc(ptr, layout);
+
+        // Mesmo lugar, inteiro abaixo da marca
+        let again = a.alloc_zeroed(layout);
+        assert_eq!(again, ptr);
+        assert!((0..layout.size()).all(|i| *again.add(i) == 0));
+        a.dealloc(again, layout);
+    }
+    assert_eq!(a.stats().live_bytes, 0);
+}
+
+#[test]
+fn slot_table_zeroes_a_reused_block() {
+    static A: AlphaAlocator<MEM, 0, SpinLock, SlotTable> = AlphaAlocator::new();
+    reused_block_is_zeroed(&A);
+
+    // Um bloco maior no mesmo lugar: só o começo tava abaixo da marca
+    let small = Layout::from_size_align(100, 8).unwrap();
+    let large = Layout::from_size_align(200, 8).unwrap();
+    unsafe {
+        let ptr = A.alloc(small);
+        ptr.write_bytes(0xAB, small.size());
+        A.dealloc(ptr, small);
+        let grown = A.alloc_zeroed(large);
+        assert_eq!(grown, ptr);
+        assert!((0..large.size()).all(|i| *grown.add(i) == 0));
+        A.dealloc(grown, large);
+    }
+}
+
+#[test]
+fn buddy_zeroes_a_reused_block() {
+    static A: AlphaAlocator<MEM, 0, SpinLock, Buddy> = AlphaAlocator::new();
+    reused_block_is_zeroed(&A);
+}
+
+#[test]
+fn tlsf_zeroes_a_reused_block() {
+    static A: AlphaAlocator<MEM, 0, SpinLock, Tlsf> = AlphaAlocator::new();
+    reused_block_is_zeroed(&A);
+}
+
+#[test]
+fn slot_table_hands_back_its_area_zeroed() {
+    static A: AlphaAlocator<MEM, 0, SpinLock, SlotTable> = AlphaAlocator::new();
+    let layout = Layout::from_size_align(8, 8).unwrap();
+    unsafe {
+        // Blocos pequenos embaixo fazem a tabela crescer lá em cima...
+        let ptrs: Vec<_> = (0..64).map(|_| A.alloc(layout)).collect();
+        assert!(ptrs.iter().all(|p| !p.is_null()), "{:?}", A.last_failure());
+        // ...e soltando tudo ela encolhe, deixando entradas velhas pra trás
+        for &ptr in &ptrs {
+            A.dealloc(ptr, layout);
+        }
+        assert!(A.stats().metadata_bytes < 64 * 8);
+
+        // Esse bloco cobre a área que era da tabela, acima da marca (512)
+        let size = MEM - A.stats().metadata_bytes;
+        let all = Layout::from_size_align(size, 8).unwrap();
+        let ptr = A.alloc_zeroed(all);
+        assert!(!ptr.is_null(), "{:?}", A.last_failure());
+        assert!((0..size).all(|i| *ptr.add(i) == 0));
+        A.dealloc(ptr, all);
+    }
+}